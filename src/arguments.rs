@@ -1,7 +1,10 @@
 use crate::database::Mssql;
 use crate::encode::Encode;
+use crate::error::Error;
 use crate::io::MssqlBufMutExt;
 use crate::protocol::rpc::StatusFlags;
+use crate::protocol::type_info::DataType;
+use crate::MssqlTypeInfo;
 use sqlx_core::arguments::Arguments;
 use sqlx_core::types::Type;
 
@@ -13,28 +16,65 @@ pub struct MssqlArguments {
     name: String,
     pub(crate) data: Vec<u8>,
     pub(crate) declarations: String,
+    // the first parameter type that could not be declared; reported when the query is run
+    unsupported: Option<MssqlTypeInfo>,
+    // parameters bound with [add_named]; the query must not use any other placeholder
+    named: Vec<String>,
+    // the first name given to [add_named] that is not a valid parameter name
+    invalid_name: Option<String>,
+    // why the first value that could not be encoded could not be
+    invalid_value: Option<String>,
 }
 
 impl MssqlArguments {
-    pub(crate) fn add_named<'q, T: Encode<'q, Mssql> + Type<Mssql>>(
+    /// Binds a value to the named parameter `name`, such as `@customerId`.
+    ///
+    /// The leading `@` may be left off. Named and positional (`@p1`, `@p2`, ...) parameters
+    /// can be mixed; running a query that uses a named parameter without a value bound to it
+    /// fails with an error naming that parameter, as does binding a name more than once
+    /// (compared case-insensitively).
+    pub fn add_named<'q, T>(&mut self, name: &str, value: T)
+    where
+        T: 'q + Encode<'q, Mssql> + Type<Mssql>,
+    {
+        let name = param_name(name);
+        self.check_name(&name);
+
+        self.name.clear();
+        self.name.push_str(&name);
+        self.named.push(name);
+
+        self.push(value);
+    }
+
+    // remembers the name if it is not a valid parameter name; reported when the query is run
+    pub(crate) fn check_name(&mut self, name: &str) {
+        if !is_valid_name(name) && self.invalid_name.is_none() {
+            self.invalid_name = Some(name.to_owned());
+        }
+    }
+
+    // writes a parameter of an RPC request, without declaring it
+    pub(crate) fn add_param<'q, T: Encode<'q, Mssql> + Type<Mssql>>(
         &mut self,
         name: &str,
         value: T,
     ) {
         let ty = value.produces().unwrap_or_else(T::type_info);
 
-        let mut ty_name = String::new();
-        ty.0.fmt(&mut ty_name);
-
         self.data.put_b_varchar(name); // [ParamName]
         self.data.push(0); // [StatusFlags]
 
         ty.0.put(&mut self.data); // [TYPE_INFO]
-        ty.0.put_value(&mut self.data, value); // [ParamLenData]
+
+        // [ParamLenData]
+        if let Err(error) = ty.0.put_value(&mut self.data, value) {
+            self.invalid_value.get_or_insert(error);
+        }
     }
 
     pub(crate) fn add_unnamed<'q, T: Encode<'q, Mssql> + Type<Mssql>>(&mut self, value: T) {
-        self.add_named("", value);
+        self.add_param("", value);
     }
 
     pub(crate) fn declare<'q, T: Encode<'q, Mssql> + Type<Mssql>>(
@@ -44,14 +84,84 @@ impl MssqlArguments {
     ) {
         let ty = initial_value.produces().unwrap_or_else(T::type_info);
 
-        let mut ty_name = String::new();
-        ty.0.fmt(&mut ty_name);
+        self.declare_as(name, ty, initial_value);
+    }
 
+    // declares an OUTPUT parameter of the given type
+    pub(crate) fn declare_as<'q, T: Encode<'q, Mssql>>(
+        &mut self,
+        name: &str,
+        ty: MssqlTypeInfo,
+        initial_value: T,
+    ) {
         self.data.put_b_varchar(name); // [ParamName]
         self.data.push(StatusFlags::BY_REF_VALUE.bits()); // [StatusFlags]
 
         ty.0.put(&mut self.data); // [TYPE_INFO]
-        ty.0.put_value(&mut self.data, initial_value); // [ParamLenData]
+
+        // [ParamLenData]
+        if let Err(error) = ty.0.put_value(&mut self.data, initial_value) {
+            self.invalid_value.get_or_insert(error);
+        }
+    }
+
+    // fails if any of the parameters has a type that cannot be declared or a value that
+    // cannot be encoded, if a name is bound more than once, or if the query uses a named
+    // parameter that has no value bound to it
+    pub(crate) fn check(&self, sql: &str) -> Result<(), Error> {
+        if let Some(ty) = &self.unsupported {
+            ty.0.fmt(&mut String::new())?;
+        }
+
+        if let Some(error) = &self.invalid_value {
+            return Err(Error::Configuration(error.clone().into()));
+        }
+
+        if let Some(name) = &self.invalid_name {
+            return Err(Error::Configuration(
+                format!("invalid parameter name {:?}", name).into(),
+            ));
+        }
+
+        // SQL Server compares the names case-insensitively, so `@Id` and `@id` are the same
+        for (i, name) in self.named.iter().enumerate() {
+            let positional = self.is_positional(name);
+
+            if positional || self.named[..i].iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                return Err(Error::Configuration(
+                    format!("more than one value was bound for the parameter {}", name).into(),
+                ));
+            }
+        }
+
+        if self.named.is_empty() {
+            // positional parameters are left for the server to check
+            return Ok(());
+        }
+
+        for name in placeholders(sql) {
+            let positional = self.is_positional(name);
+
+            if !positional && !self.named.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                return Err(Error::Configuration(
+                    format!(
+                        "no value was bound for the parameter {} used in the query",
+                        name
+                    )
+                    .into(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // whether the name is that of a positional parameter, @p1 to @pN, in any case
+    fn is_positional(&self, name: &str) -> bool {
+        name.strip_prefix("@p")
+            .or_else(|| name.strip_prefix("@P"))
+            .and_then(|n| n.parse::<usize>().ok())
+            .is_some_and(|n| n > 0 && n <= self.ordinal)
     }
 
     pub(crate) fn append(&mut self, arguments: &mut MssqlArguments) {
@@ -63,8 +173,6 @@ impl MssqlArguments {
     where
         T: Encode<'q, Mssql> + Type<Mssql>,
     {
-        let ty = value.produces().unwrap_or_else(T::type_info);
-
         // produce an ordinal parameter name
         //  @p1, @p2, ... @pN
 
@@ -74,6 +182,27 @@ impl MssqlArguments {
         self.ordinal += 1;
         self.name.push_str(itoa::Buffer::new().format(self.ordinal));
 
+        self.push(value);
+    }
+
+    // declares and writes out the parameter named by [name]
+    fn push<'q, T>(&mut self, value: T)
+    where
+        T: Encode<'q, Mssql> + Type<Mssql>,
+    {
+        let mut ty = value.produces().unwrap_or_else(T::type_info);
+
+        // like SqlClient, declare variable-length values at a fixed length, e.g. `nvarchar(4000)`,
+        // or as `nvarchar(max)` when they do not fit, so the declarations (and with them the
+        // handle of a prepared statement) are the same whatever the length of each value
+        if matches!(
+            ty.0.ty,
+            DataType::NVarChar | DataType::BigVarChar | DataType::BigVarBinary
+        ) && ty.0.size != 0xFFFF
+        {
+            ty.0.size = 8000;
+        }
+
         let MssqlArguments {
             ref name,
             ref mut declarations,
@@ -82,15 +211,18 @@ impl MssqlArguments {
         } = self;
 
         // add this to our variable declaration list
-        //  @p1 int, @p2 nvarchar(10), ...
+        //  @p1 int, @p2 nvarchar(4000), ...
 
         if !declarations.is_empty() {
-            declarations.push_str(",");
+            declarations.push(',');
         }
 
         declarations.push_str(name);
         declarations.push(' ');
-        ty.0.fmt(declarations);
+
+        if ty.0.fmt(declarations).is_err() && self.unsupported.is_none() {
+            self.unsupported = Some(ty.clone());
+        }
 
         // write out the parameter
 
@@ -98,10 +230,165 @@ impl MssqlArguments {
         data.push(0); // [StatusFlags]
 
         ty.0.put(data); // [TYPE_INFO]
-        ty.0.put_value(data, value); // [ParamLenData]
+
+        // [ParamLenData]
+        if let Err(error) = ty.0.put_value(data, value) {
+            self.invalid_value.get_or_insert(error);
+        }
+    }
+}
+
+// prefixes the name of a parameter with `@`, if it isn't already
+pub(crate) fn param_name(name: &str) -> String {
+    if name.starts_with('@') {
+        name.to_owned()
+    } else {
+        format!("@{name}")
     }
 }
 
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '@' | '#' | '$')
+}
+
+fn is_valid_name(name: &str) -> bool {
+    name.len() > 1 && name[1..].chars().all(is_ident_char) && !name[1..].starts_with('@')
+}
+
+// statements that end the list of variables of a DECLARE
+const STATEMENT_KEYWORDS: &[&str] = &[
+    "BEGIN", "DECLARE", "DELETE", "EXEC", "EXECUTE", "IF", "INSERT", "MERGE", "PRINT", "RETURN",
+    "SELECT", "SET", "UPDATE", "WHILE", "WITH",
+];
+
+// collects the variables the query uses without declaring them itself, i.e. its parameters
+//
+// string literals, quoted identifiers, comments and system functions such as `@@ROWCOUNT`
+// are skipped over
+pub(crate) fn placeholders(sql: &str) -> Vec<&str> {
+    let mut used = Vec::new();
+    let mut locals = Vec::new();
+
+    // inside a DECLARE, and whether the next variable is one being declared
+    let mut declaring = false;
+    let mut declares_next = false;
+    let mut depth = 0_usize;
+
+    let mut chars = sql.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '\'' | '"' | '[' => {
+                let end = if c == '[' { ']' } else { c };
+
+                // an escaped quote reads as two adjacent literals, which is just as well
+                for (_, c) in chars.by_ref() {
+                    if c == end {
+                        break;
+                    }
+                }
+
+                declares_next = false;
+            }
+
+            '-' if chars.peek().is_some_and(|(_, c)| *c == '-') => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+
+            '/' if chars.peek().is_some_and(|(_, c)| *c == '*') => {
+                chars.next();
+
+                let mut prev = ' ';
+
+                for (_, c) in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+
+                    prev = c;
+                }
+            }
+
+            '@' | '_' | '#' | '$' => {
+                let mut end = start + c.len_utf8();
+
+                while let Some((i, c)) = chars.next_if(|(_, c)| is_ident_char(*c)) {
+                    end = i + c.len_utf8();
+                }
+
+                let word = &sql[start..end];
+
+                if c == '@' && !word.starts_with("@@") && word.len() > 1 {
+                    if declares_next {
+                        locals.push(word);
+                    } else {
+                        used.push(word);
+                    }
+                }
+
+                declares_next = false;
+            }
+
+            c if c.is_alphanumeric() => {
+                let mut end = start + c.len_utf8();
+
+                while let Some((i, c)) = chars.next_if(|(_, c)| is_ident_char(*c)) {
+                    end = i + c.len_utf8();
+                }
+
+                let word = &sql[start..end];
+
+                if word.eq_ignore_ascii_case("DECLARE") {
+                    declaring = true;
+                    declares_next = true;
+                    depth = 0;
+                } else {
+                    if STATEMENT_KEYWORDS
+                        .iter()
+                        .any(|keyword| word.eq_ignore_ascii_case(keyword))
+                    {
+                        declaring = false;
+                    }
+
+                    declares_next = false;
+                }
+            }
+
+            '(' => {
+                depth += 1;
+                declares_next = false;
+            }
+
+            ')' => {
+                depth = depth.saturating_sub(1);
+                declares_next = false;
+            }
+
+            ',' => {
+                declares_next = declaring && depth == 0;
+            }
+
+            ';' => {
+                declaring = false;
+                declares_next = false;
+            }
+
+            c if c.is_whitespace() => {}
+
+            _ => {
+                declares_next = false;
+            }
+        }
+    }
+
+    used.retain(|name| !locals.iter().any(|local| local.eq_ignore_ascii_case(name)));
+    used
+}
+
 impl<'q> Arguments<'q> for MssqlArguments {
     type Database = Mssql;
 
@@ -116,3 +403,89 @@ impl<'q> Arguments<'q> for MssqlArguments {
         self.add(value)
     }
 }
+
+#[test]
+fn test_placeholders() {
+    assert_eq!(
+        placeholders("SELECT * FROM orders WHERE customer_id = @customerId AND placed >= @from"),
+        ["@customerId", "@from"]
+    );
+
+    assert_eq!(
+        placeholders(
+            "SELECT '@a', [@b], \"@c\", @@ROWCOUNT -- @d
+             /* @e */ FROM t WHERE x = @f"
+        ),
+        ["@f"]
+    );
+
+    assert_eq!(
+        placeholders(
+            "DECLARE @total int = @start, @t TABLE (a int, b int);
+             SELECT @total = COUNT(*) FROM @t WHERE a > @min"
+        ),
+        ["@start", "@min"]
+    );
+
+    assert_eq!(placeholders("DECLARE @x int SELECT @x, @y"), ["@y"]);
+}
+
+#[test]
+fn test_check_named() {
+    let mut args = MssqlArguments::default();
+    args.add_named("customerId", 1_i32);
+    args.add(2_i32);
+
+    assert_eq!(args.declarations, "@customerId int,@p1 int");
+    assert!(args
+        .check("SELECT * FROM t WHERE a = @CustomerId AND b = @p1")
+        .is_ok());
+
+    let err = args
+        .check("SELECT * FROM t WHERE a = @customerId AND b < @to")
+        .unwrap_err();
+    assert!(matches!(&err, Error::Configuration(_)));
+    assert!(err.to_string().contains("@to"));
+
+    let mut args = MssqlArguments::default();
+    args.add_named("@Id", 1_i32);
+    args.add_named("id", 2_i32);
+    assert!(matches!(
+        args.check("SELECT @id"),
+        Err(Error::Configuration(_))
+    ));
+
+    let mut args = MssqlArguments::default();
+    args.add(1_i32);
+    args.add_named("P1", 2_i32);
+    assert!(matches!(
+        args.check("SELECT @p1"),
+        Err(Error::Configuration(_))
+    ));
+
+    let mut args = MssqlArguments::default();
+    args.add_named("id; DROP TABLE t", 1_i32);
+    assert!(matches!(
+        args.check("SELECT 1"),
+        Err(Error::Configuration(_))
+    ));
+}
+
+#[test]
+fn test_declare_variable_length() {
+    let mut short = MssqlArguments::default();
+    short.add("hello");
+    short.add(&b"\x01"[..]);
+
+    let mut long = MssqlArguments::default();
+    long.add("hello, world");
+    long.add(&[0_u8; 100][..]);
+
+    assert_eq!(short.declarations, "@p1 nvarchar(4000),@p2 varbinary(8000)");
+    assert_eq!(short.declarations, long.declarations);
+
+    let mut max = MssqlArguments::default();
+    max.add("x".repeat(4001));
+
+    assert_eq!(max.declarations, "@p1 nvarchar(max)");
+}
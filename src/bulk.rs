@@ -0,0 +1,510 @@
+use crate::error::Error;
+use crate::io::MssqlBufMutExt;
+use crate::protocol::col_meta_data::Flags;
+use crate::protocol::done::Status;
+use crate::protocol::message::Message;
+use crate::protocol::packet::PacketType;
+use crate::protocol::sql_batch::SqlBatch;
+use crate::types::{MssqlTableRow, MssqlTableValue};
+use crate::MssqlConnection;
+
+// rows are buffered up to this many before being written out
+const BUFFERED_ROWS: usize = 1000;
+
+/// A bulk insert of rows into a table, sent as an `INSERT BULK` statement followed by a
+/// stream of rows.
+///
+/// This is the same path taken by the `bcp` utility and is much faster than inserting
+/// rows one by one. Rows are added as tuples whose values line up with the columns given
+/// to [`MssqlConnection::bulk_insert`]; the column types are taken from the values of the
+/// first rows added, as for a [`MssqlTableValue`].
+///
+/// Rows are written out while they are being added. With a [`batch_size`][Self::batch_size],
+/// each batch is a separate `INSERT BULK` statement (and so, outside of a transaction, is
+/// committed on its own). [`finish`][Self::finish] ends the last batch.
+///
+/// Dropping the bulk insert in the middle of a batch, without calling `finish`, leaves the
+/// connection with a message that cannot be ended without inserting the rows written out
+/// so far. The connection can no longer be used then: every later query fails, and a
+/// pooled connection is closed instead of going back to the pool. Batches that were ended
+/// before stay inserted, unless the bulk insert runs in a transaction, which the server
+/// rolls back once the connection is closed.
+///
+/// The table name may be qualified with a schema and a database, as in `db.dbo.events`;
+/// each part, like each column name, is quoted as an identifier unless it already is, as
+/// in `[my table]`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use welds_sqlx_mssql::MssqlConnection;
+/// # async fn example(conn: &mut MssqlConnection) -> Result<(), sqlx_core::Error> {
+/// let mut bulk = conn
+///     .bulk_insert("dbo.events", &["id", "name"])
+///     .tablock(true)
+///     .batch_size(100_000);
+///
+/// for id in 0..1_000_000_i32 {
+///     bulk.send((id, format!("event {id}"))).await?;
+/// }
+///
+/// let rows_affected = bulk.finish().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct MssqlBulkInsert<'c> {
+    conn: &'c mut MssqlConnection,
+    table: String,
+    columns: Vec<String>,
+    tablock: bool,
+    check_constraints: bool,
+    fire_triggers: bool,
+    keep_nulls: bool,
+    batch_size: Option<usize>,
+    // rows added but not yet encoded
+    rows: MssqlTableValue,
+    // the part of the bulk load message not yet written out
+    buf: Vec<u8>,
+    // whether an INSERT BULK is waiting for the end of its rows
+    in_batch: bool,
+    batch_rows: usize,
+    rows_affected: u64,
+}
+
+impl MssqlConnection {
+    /// Starts a bulk insert of rows into the given columns of the given table.
+    ///
+    /// Nothing is sent to the server until rows are added; see [`MssqlBulkInsert`].
+    pub fn bulk_insert<'c>(&'c mut self, table: &str, columns: &[&str]) -> MssqlBulkInsert<'c> {
+        MssqlBulkInsert {
+            conn: self,
+            table: table.to_owned(),
+            columns: columns.iter().map(|column| (*column).to_owned()).collect(),
+            tablock: false,
+            check_constraints: false,
+            fire_triggers: false,
+            keep_nulls: false,
+            batch_size: None,
+            rows: MssqlTableValue::new(""),
+            buf: Vec::new(),
+            in_batch: false,
+            batch_rows: 0,
+            rows_affected: 0,
+        }
+    }
+}
+
+impl MssqlBulkInsert<'_> {
+    /// Takes a table-level lock for the duration of each batch, instead of row locks.
+    ///
+    /// This is the `TABLOCK` hint, by default off.
+    pub fn tablock(mut self, tablock: bool) -> Self {
+        self.tablock = tablock;
+        self
+    }
+
+    /// Checks the constraints of the table against the inserted rows.
+    ///
+    /// This is the `CHECK_CONSTRAINTS` hint, by default off; the constraints are then
+    /// marked as not trusted.
+    pub fn check_constraints(mut self, check_constraints: bool) -> Self {
+        self.check_constraints = check_constraints;
+        self
+    }
+
+    /// Fires the insert triggers of the table.
+    ///
+    /// This is the `FIRE_TRIGGERS` hint, by default off.
+    pub fn fire_triggers(mut self, fire_triggers: bool) -> Self {
+        self.fire_triggers = fire_triggers;
+        self
+    }
+
+    /// Inserts `NULL` values as `NULL`, rather than using the default values of the columns.
+    ///
+    /// This is the `KEEP_NULLS` hint, by default off.
+    pub fn keep_nulls(mut self, keep_nulls: bool) -> Self {
+        self.keep_nulls = keep_nulls;
+        self
+    }
+
+    /// Sends the rows in batches of the given number of rows, each its own `INSERT BULK`.
+    ///
+    /// By default, all rows are sent in a single batch.
+    pub fn batch_size(mut self, rows: usize) -> Self {
+        self.batch_size = Some(rows).filter(|rows| *rows > 0);
+        self
+    }
+
+    /// Adds a row, writing out the rows added before it once enough of them have been.
+    ///
+    /// Fails, without adding the row, if it has a different number of values than the rows
+    /// before it or a value of a different type than the values before it in the same column.
+    pub async fn send(&mut self, row: impl MssqlTableRow) -> Result<(), Error> {
+        self.rows.try_add_row(row)?;
+
+        let batch_full = self
+            .batch_size
+            .is_some_and(|size| self.batch_rows + self.rows.len() >= size);
+
+        if batch_full || self.rows.len() >= BUFFERED_ROWS {
+            self.write_rows().await?;
+        }
+
+        if batch_full {
+            self.end_batch().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes out the remaining rows and ends the last batch, returning the number of rows
+    /// inserted by the bulk insert.
+    pub async fn finish(mut self) -> Result<u64, Error> {
+        self.write_rows().await?;
+
+        if self.in_batch {
+            self.end_batch().await?;
+        }
+
+        Ok(self.rows_affected)
+    }
+
+    // the INSERT BULK statement that the rows of a batch follow
+    fn statement(&self) -> Result<String, Error> {
+        let mut sql = format!("INSERT BULK {} (", quote_table(&self.table)?);
+
+        for (i, (column, ty)) in self
+            .columns
+            .iter()
+            .zip(self.rows.column_types())
+            .enumerate()
+        {
+            if i > 0 {
+                sql.push_str(", ");
+            }
+
+            sql.push_str(&quote(&column_name(column)?));
+            sql.push(' ');
+            ty.0.fmt(&mut sql)?;
+        }
+
+        sql.push(')');
+
+        let hints = [
+            (self.tablock, "TABLOCK"),
+            (self.check_constraints, "CHECK_CONSTRAINTS"),
+            (self.fire_triggers, "FIRE_TRIGGERS"),
+            (self.keep_nulls, "KEEP_NULLS"),
+        ];
+
+        let mut hints = hints.iter().filter(|(on, _)| *on).map(|(_, hint)| *hint);
+
+        if let Some(hint) = hints.next() {
+            sql.push_str(" WITH (");
+            sql.push_str(hint);
+
+            for hint in hints {
+                sql.push_str(", ");
+                sql.push_str(hint);
+            }
+
+            sql.push(')');
+        }
+
+        Ok(sql)
+    }
+
+    // sends the INSERT BULK statement and starts the message with the rows
+    async fn begin_batch(&mut self) -> Result<(), Error> {
+        let len = self.rows.column_types().len();
+
+        if len != self.columns.len() {
+            return Err(Error::Configuration(
+                format!(
+                    "bulk insert into {} columns was given rows of {} values",
+                    self.columns.len(),
+                    len
+                )
+                .into(),
+            ));
+        }
+
+        // the types are declared by the statement, so later rows must stick to them
+        self.rows.freeze();
+
+        let sql = self.statement()?;
+
+        self.conn.stream.wait_until_ready().await?;
+        self.conn.stream.pending_done_count += 1;
+
+        self.conn.stream.write_packet(
+            PacketType::SqlBatch,
+            SqlBatch {
+                transaction_descriptor: self.conn.stream.transaction_descriptor,
+                sql: &sql,
+            },
+        );
+
+        self.conn.stream.flush().await?;
+        self.recv_done().await?;
+
+        // [COLMETADATA]
+        self.buf.push(0x81);
+        self.buf.extend(&(len as u16).to_le_bytes()); // [Count]
+
+        for (column, ty) in self.columns.iter().zip(self.rows.column_types()) {
+            self.buf.extend(&0_u32.to_le_bytes()); // [UserType]
+            self.buf.extend(&Flags::NULLABLE.bits().to_le_bytes()); // [Flags]
+            ty.0.put(&mut self.buf); // [TYPE_INFO]
+            self.buf.put_b_varchar(&column_name(column)?); // [ColName]
+        }
+
+        self.in_batch = true;
+
+        Ok(())
+    }
+
+    // writes out the rows added so far, as whole packets of the message of the batch
+    async fn write_rows(&mut self) -> Result<(), Error> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+
+        if !self.in_batch {
+            self.begin_batch().await?;
+        }
+
+        self.rows.put_rows(&mut self.buf, 0xd1); // [ROW]
+        self.batch_rows += self.rows.len();
+        self.rows.clear();
+
+        self.conn
+            .stream
+            .write_message_part(PacketType::BulkLoadData, &mut self.buf, false);
+
+        self.conn.stream.flush().await?;
+
+        Ok(())
+    }
+
+    // ends the message of the batch and waits for the rows to have been inserted
+    async fn end_batch(&mut self) -> Result<(), Error> {
+        self.end_message();
+        self.conn.stream.flush().await?;
+
+        self.recv_done().await
+    }
+
+    fn end_message(&mut self) {
+        // [DONE]
+        self.buf.push(0xfd);
+        self.buf.extend(&0_u16.to_le_bytes()); // [Status]
+        self.buf.extend(&0_u16.to_le_bytes()); // [CurCmd]
+        self.buf.extend(&0_u64.to_le_bytes()); // [DoneRowCount]
+
+        self.conn
+            .stream
+            .write_message_part(PacketType::BulkLoadData, &mut self.buf, true);
+
+        self.conn.stream.pending_done_count += 1;
+
+        self.in_batch = false;
+        self.batch_rows = 0;
+    }
+
+    async fn recv_done(&mut self) -> Result<(), Error> {
+        loop {
+            match self.conn.stream.recv_message().await? {
+                Message::Done(done) | Message::DoneProc(done)
+                    if !done.status.contains(Status::DONE_MORE) =>
+                {
+                    self.rows_affected += done.affected_rows;
+                    self.conn.stream.handle_done(&done);
+
+                    return Ok(());
+                }
+
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for MssqlBulkInsert<'_> {
+    fn drop(&mut self) {
+        if self.in_batch {
+            // ending the message would insert the rows of the batch, which were meant to be
+            // abandoned along with the bulk insert
+            self.conn.stream.broken = true;
+        }
+    }
+}
+
+// splits a name made of parts separated by dots, such as `db.dbo.[my table]`, into those
+// parts, unquoted; fails on an empty part or on a quoted part that is not closed
+fn split_name(name: &str) -> Option<Vec<String>> {
+    let mut parts = Vec::new();
+    let mut chars = name.chars().peekable();
+
+    loop {
+        let mut part = String::new();
+
+        if chars.next_if_eq(&'[').is_some() {
+            loop {
+                match chars.next()? {
+                    ']' if chars.next_if_eq(&']').is_some() => part.push(']'),
+                    ']' => break,
+                    c => part.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != '.') {
+                part.push(c);
+            }
+        }
+
+        if part.is_empty() {
+            return None;
+        }
+
+        parts.push(part);
+
+        match chars.next() {
+            None => return Some(parts),
+            Some('.') => {}
+            Some(_) => return None,
+        }
+    }
+}
+
+// the name of a table, optionally qualified with a schema and a database, quoted
+fn quote_table(name: &str) -> Result<String, Error> {
+    match split_name(name) {
+        Some(parts) if parts.len() <= 3 => Ok(parts
+            .iter()
+            .map(|part| quote(part))
+            .collect::<Vec<_>>()
+            .join(".")),
+
+        _ => Err(Error::Configuration(
+            format!("invalid table name {:?}", name).into(),
+        )),
+    }
+}
+
+// the name of a column, unquoted
+fn column_name(name: &str) -> Result<String, Error> {
+    match split_name(name) {
+        Some(mut parts) if parts.len() == 1 => Ok(parts.remove(0)),
+
+        _ => Err(Error::Configuration(
+            format!("invalid column name {:?}", name).into(),
+        )),
+    }
+}
+
+// quotes a name as an identifier
+fn quote(name: &str) -> String {
+    format!("[{}]", name.replace(']', "]]"))
+}
+
+#[test]
+fn test_quote() {
+    assert_eq!(quote_table("events").unwrap(), "[events]");
+    assert_eq!(
+        quote_table("db.dbo.[my table]").unwrap(),
+        "[db].[dbo].[my table]"
+    );
+    assert_eq!(quote_table("[a.b]]c]").unwrap(), "[a.b]]c]");
+
+    for name in [
+        "",
+        "a..b",
+        "a.b.c.d",
+        "[a",
+        "[a]b",
+        "[x] int); DROP TABLE t; --]",
+    ] {
+        assert!(matches!(quote_table(name), Err(Error::Configuration(_))));
+    }
+
+    assert_eq!(column_name("id").unwrap(), "id");
+    assert_eq!(column_name("[a]]b]").unwrap(), "a]b");
+    assert_eq!(column_name("a]b").unwrap(), "a]b");
+    assert!(column_name("a.b").is_err());
+}
+
+#[test]
+fn test_bulk_insert() {
+    use crate::connection::block_on;
+    use crate::connection::{tabular_result, written_messages};
+
+    let mut response = tabular_result(&[0xfd, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    response.extend(tabular_result(&[
+        0xfd, 0x10, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, // DONE with 2 rows
+    ]));
+
+    let (mut conn, written) = MssqlConnection::mock(response);
+
+    let mut bulk = conn
+        .bulk_insert("dbo.[my table]", &["id", "[name]"])
+        .tablock(true)
+        .keep_nulls(true);
+
+    block_on(bulk.send((1_i32, "a"))).unwrap();
+    block_on(bulk.send((2_i32, None::<&str>))).unwrap();
+
+    assert_eq!(block_on(bulk.finish()).unwrap(), 2);
+
+    let messages = written_messages(&written.lock().unwrap());
+    assert_eq!(messages.len(), 2);
+
+    let (ty, payload) = &messages[0];
+    let headers_len = u32::from_le_bytes(payload[..4].try_into().unwrap()) as usize;
+    let sql: Vec<u16> = payload[headers_len..]
+        .chunks(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+
+    assert_eq!(*ty, PacketType::SqlBatch as u8);
+    assert_eq!(
+        String::from_utf16(&sql).unwrap(),
+        "INSERT BULK [dbo].[my table] ([id] int, [name] nvarchar(max)) WITH (TABLOCK, KEEP_NULLS)"
+    );
+
+    let (ty, payload) = &messages[1];
+    assert_eq!(*ty, PacketType::BulkLoadData as u8);
+
+    #[rustfmt::skip]
+    assert_eq!(payload, &[
+        0x81, 2, 0, // COLMETADATA of 2 columns
+        0, 0, 0, 0, 1, 0, 0x26, 4, 2, b'i', 0, b'd', 0, // id int
+        0, 0, 0, 0, 1, 0, 0xe7, 0xff, 0xff, 0x09, 0x04, 0xd0, 0x00, 0x34, // name nvarchar(max)
+        4, b'n', 0, b'a', 0, b'm', 0, b'e', 0,
+        0xd1, 4, 1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, b'a', 0, 0, 0, 0, 0, // (1, 'a')
+        0xd1, 4, 2, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // (2, NULL)
+        0xfd, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // DONE
+    ][..]);
+}
+
+#[test]
+fn test_bulk_insert_dropped() {
+    use crate::connection::block_on;
+    use crate::connection::tabular_result;
+    use sqlx_core::connection::Connection;
+
+    let response = tabular_result(&[0xfd, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    let (mut conn, _) = MssqlConnection::mock(response);
+
+    let mut bulk = conn.bulk_insert("events", &["id"]);
+
+    // enough rows for them to be written out, starting a batch
+    for id in 0..BUFFERED_ROWS as i32 {
+        block_on(bulk.send((id,))).unwrap();
+    }
+
+    drop(bulk);
+
+    assert!(block_on(conn.ping()).is_err());
+}
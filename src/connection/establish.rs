@@ -1,70 +1,102 @@
 use crate::common::StatementCache;
 use crate::connection::stream::MssqlStream;
 use crate::error::Error;
+use crate::net::tls;
 use crate::protocol::login::Login7;
 use crate::protocol::message::Message;
 use crate::protocol::packet::PacketType;
 use crate::protocol::pre_login::{Encrypt, PreLogin, Version};
-use crate::{MssqlConnectOptions, MssqlConnection};
+use crate::{MssqlApplicationIntent, MssqlConnectOptions, MssqlConnection, MssqlEncryption};
 use sqlx_core::io::Decode;
 
 impl MssqlConnection {
     pub(crate) async fn establish(options: &MssqlConnectOptions) -> Result<Self, Error> {
-        let mut stream: MssqlStream = MssqlStream::connect(options).await?;
-
-        // Send PRELOGIN to set up the context for login. The server should immediately
-        // respond with a PRELOGIN message of its own.
+        if !(512..=32767).contains(&options.packet_size) {
+            return Err(Error::Configuration(
+                format!(
+                    "`packet_size` must be between 512 and 32767, got {}",
+                    options.packet_size
+                )
+                .into(),
+            ));
+        }
 
-        // TODO: Encryption
-        // TODO: Send the version of SQLx over
+        let mut stream: MssqlStream = MssqlStream::connect(options).await?;
 
-        stream.write_packet(
-            PacketType::PreLogin,
-            PreLogin {
-                version: Version::default(),
-                encryption: Encrypt::NOT_SUPPORTED,
+        let server_encryption = pre_login(
+            &mut stream,
+            options.ssl_mode.requested_encryption(tls::available()),
+            options,
+        )
+        .await?;
+
+        let encryption = options
+            .ssl_mode
+            .negotiate(server_encryption, tls::available())?;
+        let mut login_socket = None;
+
+        match encryption {
+            MssqlEncryption::On => {
+                stream = stream.upgrade(options).await?;
+            }
 
-                ..Default::default()
-            },
-        );
+            MssqlEncryption::LoginOnly => {
+                let (upgraded, socket) = stream.upgrade_for_login(options).await?;
 
-        stream.flush().await?;
+                stream = upgraded;
+                login_socket = Some(socket);
+            }
 
-        let (_, packet) = stream.recv_packet().await?;
-        let _ = PreLogin::decode(packet)?;
+            MssqlEncryption::Off | MssqlEncryption::Strict => {}
+        }
 
         // LOGIN7 defines the authentication rules for use between client and server
 
         stream.write_packet(
             PacketType::Tds7Login,
             Login7 {
-                // FIXME: use a version constant
-                version: 0x74000004, // SQL Server 2012 - SQL Server 2019
-                client_program_version: 0,
-                client_pid: 0,
-                packet_size: 4096,
-                hostname: "",
+                version: tds_version(encryption),
+                client_program_version: client_program_version(),
+                client_pid: std::process::id(),
+                packet_size: options.packet_size as u32,
+                hostname: &options.workstation_name,
                 username: &options.username,
                 password: options.password.as_deref().unwrap_or_default(),
-                app_name: "",
-                server_name: "",
-                client_interface_name: "",
-                language: "",
+                app_name: &options.app_name,
+                server_name: &options.host,
+                client_interface_name: &options.client_interface_name,
+                language: &options.language,
                 database: &options.database,
                 client_id: [0; 6],
+                read_only_intent: options.application_intent == MssqlApplicationIntent::ReadOnly,
             },
         );
 
         stream.flush().await?;
 
+        if let Some(socket) = login_socket {
+            // the server answers LOGIN7 in plain text
+            stream = stream.downgrade(socket)?;
+        }
+
+        stream.info_log_level = options.info_log_level;
+
         loop {
             // NOTE: we should receive an [Error] message if something goes wrong, otherwise,
             //       all messages are mostly informational (ENVCHANGE, INFO, LOGINACK)
 
             match stream.recv_message().await? {
-                Message::LoginAck(_) => {
+                Message::LoginAck(ack) => {
                     // indicates that the login was successful
-                    // no action is needed, we are just going to keep waiting till we hit <Done>
+                    // we are just going to keep waiting till we hit <Done>
+                    log::debug!(
+                        "logged in to {} {}.{}.{} with TDS version 0x{:08x}",
+                        ack.program_name.trim_end_matches('\0'),
+                        ack.program_version.major,
+                        ack.program_version.minor,
+                        ack.program_version.build,
+                        ack.tds_version,
+                    );
                 }
 
                 Message::Done(_) => {
@@ -82,7 +114,95 @@ impl MssqlConnection {
         Ok(Self {
             stream,
             cache_statement: StatementCache::new(1024),
+            cache_prepared: StatementCache::new(options.statement_cache_capacity),
+            pending_unprepare: Vec::new(),
             log_settings: options.log_settings.clone(),
+            encryption,
         })
     }
 }
+
+// Send PRELOGIN to set up the context for login. The server should immediately
+// respond with a PRELOGIN message of its own, carrying its side of the encryption
+// negotiation.
+async fn pre_login(
+    stream: &mut MssqlStream,
+    encryption: Encrypt,
+    options: &MssqlConnectOptions,
+) -> Result<Encrypt, Error> {
+    // TODO: Send the version of SQLx over
+
+    stream.write_packet(
+        PacketType::PreLogin,
+        PreLogin {
+            version: Version::default(),
+            encryption,
+            instance: options.instance_name.as_deref(),
+
+            ..Default::default()
+        },
+    );
+
+    stream.flush().await?;
+
+    let (_, packet) = stream.recv_packet().await?;
+    let pre_login = PreLogin::decode(packet)?;
+
+    if pre_login.instance_mismatch {
+        return Err(Error::Configuration(
+            format!(
+                "the server is not the SQL Server instance {:?}",
+                options.instance_name.as_deref().unwrap_or_default()
+            )
+            .into(),
+        ));
+    }
+
+    Ok(pre_login.encryption)
+}
+
+// TDS 7.4, spoken by SQL Server 2012 - SQL Server 2019
+const TDS_7_4: u32 = 0x74000004;
+
+// TDS 8.0, for strict encryption
+const TDS_8_0: u32 = 0x08000000;
+
+// the TDS version to ask for in LOGIN7
+fn tds_version(encryption: MssqlEncryption) -> u32 {
+    if encryption == MssqlEncryption::Strict {
+        TDS_8_0
+    } else {
+        TDS_7_4
+    }
+}
+
+// the version of this crate, as `major.minor.patch` in 8, 8 and 16 bits
+fn client_program_version() -> u32 {
+    let part = |part: &str| part.parse::<u32>().unwrap_or_default();
+
+    (part(env!("CARGO_PKG_VERSION_MAJOR")) & 0xff) << 24
+        | (part(env!("CARGO_PKG_VERSION_MINOR")) & 0xff) << 16
+        | (part(env!("CARGO_PKG_VERSION_PATCH")) & 0xffff)
+}
+
+#[test]
+fn test_client_program_version() {
+    let version = client_program_version();
+
+    assert_eq!(
+        format!(
+            "{}.{}.{}",
+            version >> 24,
+            (version >> 16) & 0xff,
+            version & 0xffff
+        ),
+        env!("CARGO_PKG_VERSION")
+    );
+}
+
+#[test]
+fn test_tds_version() {
+    assert_eq!(tds_version(MssqlEncryption::Strict), 0x08000000);
+    assert_eq!(tds_version(MssqlEncryption::On), 0x74000004);
+    assert_eq!(tds_version(MssqlEncryption::Off), 0x74000004);
+}
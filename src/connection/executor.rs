@@ -1,4 +1,4 @@
-use crate::connection::prepare::prepare;
+use crate::connection::prepare::{prepare, unprepare};
 use crate::describe::Describe;
 use crate::error::Error;
 use crate::executor::{Execute, Executor};
@@ -21,26 +21,73 @@ use std::borrow::Cow;
 use std::sync::Arc;
 
 impl MssqlConnection {
-    async fn run(&mut self, query: &str, arguments: Option<MssqlArguments>) -> Result<(), Error> {
+    // sends the query; when the query is being prepared, its handle follows the results as
+    // a RETURNVALUE, which the stream picks up even if the results are not read to the end
+    async fn run(
+        &mut self,
+        query: &str,
+        arguments: Option<MssqlArguments>,
+        persistent: bool,
+    ) -> Result<(), Error> {
+        if let Some(arguments) = &arguments {
+            arguments.check(query)?;
+        }
+
+        // the response to the previous query may still be carrying the handle of the
+        // statement it prepared
+        self.stream.wait_until_ready().await?;
+        self.cache_prepared_handle();
+
+        // statements evicted from the cache are released before anything else is sent
+        while let Some(handle) = self.pending_unprepare.pop() {
+            unprepare(self, handle).await?;
+        }
+
         self.stream.wait_until_ready().await?;
         self.stream.pending_done_count += 1;
 
+        let mut prepare_key = None;
+
         if let Some(mut arguments) = arguments {
-            let proc = Either::Right(Procedure::ExecuteSql);
+            let mut proc = Either::Right(Procedure::ExecuteSql);
             let mut proc_args = MssqlArguments::default();
 
-            // SQL
-            proc_args.add_unnamed(query);
+            if persistent && self.cache_prepared.is_enabled() {
+                // a handle is only valid for the parameter types it was prepared with,
+                // so those are part of the key
+                let key = format!("{}\n{}", arguments.declarations, query);
+
+                if let Some(handle) = self.cache_prepared.get_mut(&key) {
+                    // [sp_execute] a statement we have prepared before
+                    proc = Either::Right(Procedure::Execute);
+                    proc_args.add_unnamed(*handle);
+                } else {
+                    // [sp_prepexec] prepares the statement and executes it in one go
+                    proc = Either::Right(Procedure::PrepareExecute);
+                    proc_args.declare("", 0_i32);
+                    proc_args.add_unnamed(if arguments.data.is_empty() {
+                        None
+                    } else {
+                        Some(&*arguments.declarations)
+                    });
+                    proc_args.add_unnamed(query);
 
-            if !arguments.data.is_empty() {
-                // Declarations
-                //  NAME TYPE, NAME TYPE, ...
-                proc_args.add_unnamed(&*arguments.declarations);
+                    prepare_key = Some(key);
+                }
+            } else {
+                // SQL
+                proc_args.add_unnamed(query);
 
-                // Add the list of SQL parameters _after_ our RPC parameters
-                proc_args.append(&mut arguments);
+                if !arguments.data.is_empty() {
+                    // Declarations
+                    //  NAME TYPE, NAME TYPE, ...
+                    proc_args.add_unnamed(&*arguments.declarations);
+                }
             }
 
+            // Add the list of SQL parameters _after_ our RPC parameters
+            proc_args.append(&mut arguments);
+
             self.stream.write_packet(
                 PacketType::Rpc,
                 RpcRequest {
@@ -60,6 +107,7 @@ impl MssqlConnection {
             );
         }
 
+        self.stream.pending_prepare = prepare_key;
         self.stream.flush().await?;
 
         Ok(())
@@ -69,20 +117,21 @@ impl MssqlConnection {
 impl<'c> Executor<'c> for &'c mut MssqlConnection {
     type Database = Mssql;
 
-    fn fetch_many<'e, 'q: 'e, E: 'q>(
+    fn fetch_many<'e, 'q: 'e, E>(
         self,
         mut query: E,
     ) -> BoxStream<'e, Result<Either<MssqlQueryResult, MssqlRow>, Error>>
     where
         'c: 'e,
-        E: Execute<'q, Self::Database>,
+        E: 'q + Execute<'q, Self::Database>,
     {
         let sql = query.sql();
         let arguments = query.take_arguments();
+        let persistent = query.persistent();
         let mut logger = QueryLogger::new(sql, self.log_settings.clone());
 
         Box::pin(try_stream! {
-            self.run(sql, arguments).await?;
+            self.run(sql, arguments, persistent).await?;
 
             loop {
                 let message = self.stream.recv_message().await?;
@@ -111,18 +160,17 @@ impl<'c> Executor<'c> for &'c mut MssqlConnection {
                         }
 
                         if !done.status.contains(Status::DONE_MORE) {
+                            self.cache_prepared_handle();
                             break;
                         }
                     }
 
-                    Message::DoneInProc(done) => {
-                        if done.status.contains(Status::DONE_COUNT) {
-                            let rows_affected = done.affected_rows;
-                            logger.increase_rows_affected(rows_affected);
-                            r#yield!(Either::Left(MssqlQueryResult {
-                                rows_affected,
-                            }));
-                        }
+                    Message::DoneInProc(done) if done.status.contains(Status::DONE_COUNT) => {
+                        let rows_affected = done.affected_rows;
+                        logger.increase_rows_affected(rows_affected);
+                        r#yield!(Either::Left(MssqlQueryResult {
+                            rows_affected,
+                        }));
                     }
 
                     _ => {}
@@ -133,13 +181,13 @@ impl<'c> Executor<'c> for &'c mut MssqlConnection {
         })
     }
 
-    fn fetch_optional<'e, 'q: 'e, E: 'q>(
+    fn fetch_optional<'e, 'q: 'e, E>(
         self,
         query: E,
     ) -> BoxFuture<'e, Result<Option<MssqlRow>, Error>>
     where
         'c: 'e,
-        E: Execute<'q, Self::Database>,
+        E: 'q + Execute<'q, Self::Database>,
     {
         let mut s = self.fetch_many(query);
 
@@ -191,8 +239,61 @@ impl<'c> Executor<'c> for &'c mut MssqlConnection {
             Ok(Describe {
                 nullable,
                 columns: (metadata.columns).clone(),
-                parameters: None,
+                parameters: metadata.parameters.clone(),
             })
         })
     }
 }
+
+#[test]
+fn test_fetch_one_prepares_once() {
+    use crate::connection::block_on;
+    use crate::connection::stream::{tabular_result, written_messages};
+    use sqlx_core::row::Row;
+
+    // COLMETADATA with a single INT column, and a ROW of it
+    let mut results = vec![0x81, 1, 0, 0, 0, 0, 0, 0x01, 0, 0x26, 4, 1, b'x', 0];
+    results.extend(&[0xd1, 4, 1, 0, 0, 0]);
+
+    // the handle of the prepared statement, 7
+    let return_value = [
+        0xac, 0, 0, 0, 0x01, 0, 0, 0, 0, 0, 0, 0x26, 4, 4, 7, 0, 0, 0,
+    ];
+    let done_proc = [0xfe, 0x10, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0];
+
+    // [sp_prepexec]
+    let mut response = results.clone();
+    response.extend(&return_value);
+    response.extend(&done_proc);
+    let mut responses = tabular_result(&response);
+
+    // [sp_execute]
+    let mut response = results.clone();
+    response.extend(&done_proc);
+    responses.extend(tabular_result(&response));
+
+    let (mut conn, written) = MssqlConnection::mock(responses);
+
+    for _ in 0..2 {
+        let query = sqlx_core::query::query::<Mssql>("SELECT @p1 AS x").bind(1_i32);
+        let row = block_on((&mut conn).fetch_one(query)).unwrap();
+
+        assert_eq!(row.try_get::<i32, _>("x").unwrap(), 1);
+    }
+
+    // the ID of the procedure each RPC request calls, after its ALL_HEADERS
+    let procedures: Vec<u16> = written_messages(&written.lock().unwrap())
+        .into_iter()
+        .filter(|(ty, _)| *ty == PacketType::Rpc as u8)
+        .map(|(_, payload)| {
+            let headers_len = u32::from_le_bytes(payload[..4].try_into().unwrap()) as usize;
+            u16::from_le_bytes([payload[headers_len + 2], payload[headers_len + 3]])
+        })
+        .collect();
+
+    assert_eq!(
+        procedures,
+        [Procedure::PrepareExecute as u16, Procedure::Execute as u16]
+    );
+    assert_eq!(conn.cache_prepared.len(), 1);
+}
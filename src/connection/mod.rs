@@ -3,7 +3,7 @@ use crate::connection::stream::MssqlStream;
 use crate::error::Error;
 use crate::executor::Executor;
 use crate::statement::MssqlStatementMetadata;
-use crate::{Mssql, MssqlConnectOptions};
+use crate::{Mssql, MssqlConnectOptions, MssqlInfo};
 use futures_core::future::BoxFuture;
 use futures_util::{FutureExt, TryFutureExt};
 use sqlx_core::connection::{Connection, LogSettings};
@@ -14,13 +14,107 @@ use std::sync::Arc;
 mod establish;
 mod executor;
 mod prepare;
+mod ssrp;
 mod stream;
+mod strict;
 mod tls;
 
+pub use tls::MssqlEncryption;
+
+#[cfg(test)]
+pub(crate) use stream::{tabular_result, written_messages};
+
 pub struct MssqlConnection {
     pub(crate) stream: MssqlStream,
     pub(crate) cache_statement: StatementCache<Arc<MssqlStatementMetadata>>,
-    log_settings: LogSettings,
+    // handles of statements prepared for execution, keyed by their declarations and SQL
+    pub(crate) cache_prepared: StatementCache<i32>,
+    // handles evicted from [cache_prepared], to be unprepared before the next query
+    pub(crate) pending_unprepare: Vec<i32>,
+    pub(crate) log_settings: LogSettings,
+    encryption: MssqlEncryption,
+}
+
+impl MssqlConnection {
+    /// The encryption negotiated with the server when this connection was established.
+    pub fn encryption(&self) -> MssqlEncryption {
+        self.encryption
+    }
+
+    /// Sets a handler to be called with every informational message the server sends on
+    /// this connection, such as the output of `PRINT` or of a `RAISERROR` with a severity
+    /// of 10 or lower. Replaces any handler set before.
+    ///
+    /// The handler is called while the results of the query are being read, so messages
+    /// sent with `RAISERROR ... WITH NOWAIT` arrive as soon as the server flushes them.
+    ///
+    /// These messages are also logged, see
+    /// [`MssqlConnectOptions::log_info_messages`][crate::MssqlConnectOptions::log_info_messages].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # async fn example(conn: &mut welds_sqlx_mssql::MssqlConnection) {
+    /// conn.set_info_handler(|info| println!("{}: {}", info.number(), info.message()));
+    /// # }
+    /// ```
+    pub fn set_info_handler<F>(&mut self, handler: F)
+    where
+        F: FnMut(MssqlInfo) + Send + 'static,
+    {
+        self.stream.info_handler = Some(Box::new(handler));
+    }
+
+    /// Removes the handler set with [`set_info_handler`][Self::set_info_handler].
+    pub fn clear_info_handler(&mut self) {
+        self.stream.info_handler = None;
+    }
+
+    // puts the handle of a statement prepared by an earlier request into the cache
+    pub(crate) fn cache_prepared_handle(&mut self) {
+        if let Some((key, handle)) = self.stream.prepared.take() {
+            if let Some(evicted) = self.cache_prepared.insert(&key, handle) {
+                self.pending_unprepare.push(evicted);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl MssqlConnection {
+    // a connection reading the given server responses, along with what gets written to it
+    pub(crate) fn mock(response: Vec<u8>) -> (Self, Arc<std::sync::Mutex<Vec<u8>>>) {
+        let (stream, written) = MssqlStream::mock(response);
+
+        let conn = Self {
+            stream,
+            cache_statement: StatementCache::new(1024),
+            cache_prepared: StatementCache::new(1024),
+            pending_unprepare: Vec::new(),
+            log_settings: LogSettings::default(),
+            encryption: MssqlEncryption::Off,
+        };
+
+        (conn, written)
+    }
+}
+
+// runs a future that never has to wait, as is the case with a mock connection
+#[cfg(test)]
+pub(crate) fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll};
+
+    let mut future = std::pin::pin!(future);
+    let waker = futures_util::task::noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    for _ in 0..1000 {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+
+    panic!("future did not complete");
 }
 
 impl Debug for MssqlConnection {
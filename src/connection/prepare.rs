@@ -1,14 +1,19 @@
-use crate::decode::Decode;
+use crate::arguments::placeholders;
 use crate::error::Error;
+use crate::ext::ustr::UStr;
+use crate::protocol::col_meta_data::Flags;
 use crate::protocol::done::Status;
 use crate::protocol::message::Message;
 use crate::protocol::packet::PacketType;
 use crate::protocol::rpc::{OptionFlags, Procedure, RpcRequest};
+use crate::protocol::type_info::TypeInfo;
 use crate::statement::MssqlStatementMetadata;
-use crate::{Mssql, MssqlArguments, MssqlConnection, MssqlTypeInfo, MssqlValueRef};
+use crate::HashMap;
+use crate::{
+    MssqlArguments, MssqlColumn, MssqlConnection, MssqlDatabaseError, MssqlRow, MssqlTypeInfo,
+};
 use either::Either;
-use once_cell::sync::Lazy;
-use regex::Regex;
+use sqlx_core::row::Row;
 use std::sync::Arc;
 
 pub(crate) async fn prepare(
@@ -19,124 +24,295 @@ pub(crate) async fn prepare(
         return Ok(metadata.clone());
     }
 
-    // NOTE: this does not support unicode identifiers; as we don't even support
-    //       named parameters (yet) this is probably fine, for now
+    // [sp_describe_undeclared_parameters] suggests a type for each placeholder used in the query
+    let mut args = MssqlArguments::default();
+    args.add_unnamed(sql);
+
+    let described = describe(conn, "sp_describe_undeclared_parameters", &args).await;
+
+    let (params, parameters) = match described {
+        Ok(rows) => describe_parameters(&rows)?,
 
-    static PARAMS_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"@p[[:alnum:]]+").unwrap());
+        // a placeholder used more than once, or one whose type cannot be deduced, leaves
+        // the server without a suggestion; all that is known then is how many there are
+        Err(Error::Database(error))
+            if error
+                .try_downcast_ref::<MssqlDatabaseError>()
+                .is_some_and(|error| UNDESCRIBED_PARAMETERS.contains(&error.number())) =>
+        {
+            undescribed_parameters(sql)
+        }
+
+        Err(error) => return Err(error),
+    };
+
+    // [sp_describe_first_result_set] describes the columns of the result, given those parameters
+    let mut args = MssqlArguments::default();
+    args.add_unnamed(sql);
+    args.add_unnamed(if params.is_empty() {
+        None
+    } else {
+        Some(&*params)
+    });
+
+    let rows = describe(conn, "sp_describe_first_result_set", &args).await?;
+
+    let mut columns = Vec::with_capacity(rows.len());
+    let mut column_names = HashMap::with_capacity(rows.len());
+
+    for row in &rows {
+        // expressions without an alias have no name
+        let name: Option<String> = row.try_get("name")?;
+        let nullable: bool = row.try_get("is_nullable")?;
+
+        let type_info = TypeInfo::from_system_type(
+            row.try_get("system_type_id")?,
+            row.try_get("max_length")?,
+            row.try_get("precision")?,
+            row.try_get("scale")?,
+        )?;
+
+        let ordinal = columns.len();
+        let name = UStr::from(name.unwrap_or_default());
+
+        column_names.insert(name.clone(), ordinal);
+        columns.push(MssqlColumn {
+            ordinal,
+            name,
+            type_info: MssqlTypeInfo(type_info),
+            flags: if nullable {
+                Flags::NULLABLE
+            } else {
+                Flags::empty()
+            },
+        });
+    }
+
+    let metadata = Arc::new(MssqlStatementMetadata {
+        columns,
+        column_names,
+        parameters: Some(parameters),
+    });
 
+    conn.cache_statement.insert(sql, metadata.clone());
+
+    Ok(metadata)
+}
+
+// the errors [sp_describe_undeclared_parameters] raises for parameters it cannot describe
+const UNDESCRIBED_PARAMETERS: [i32; 2] = [
+    11503, // the parameter type cannot be deduced
+    11508, // the undeclared parameter is used more than once
+];
+
+// the declarations of the parameters described by [sp_describe_undeclared_parameters],
+// along with their types
+fn describe_parameters(
+    rows: &[MssqlRow],
+) -> Result<(String, Either<Vec<MssqlTypeInfo>, usize>), Error> {
     let mut params = String::new();
+    let mut parameters = Vec::with_capacity(rows.len());
+
+    for row in rows {
+        let name: String = row.try_get("name")?;
+        let type_name: String = row.try_get("suggested_system_type_name")?;
+
+        let type_info = TypeInfo::from_system_type(
+            row.try_get("suggested_system_type_id")?,
+            row.try_get("suggested_max_length")?,
+            row.try_get("suggested_precision")?,
+            row.try_get("suggested_scale")?,
+        )?;
 
-    for m in PARAMS_RE.captures_iter(sql) {
         if !params.is_empty() {
-            params.push_str(",");
+            params.push(',');
         }
 
-        params.push_str(&m[0]);
+        params.push_str(&name);
+        params.push(' ');
+        params.push_str(&type_name);
 
-        // NOTE: this means that a query! of `SELECT @p1` will have the macros believe
-        //       it will return nvarchar(1); this is a greater issue with `query!` that we
-        //       we need to circle back to. This doesn't happen much in practice however.
-        params.push_str(" nvarchar(1)");
+        parameters.push((name, MssqlTypeInfo(type_info)));
     }
 
-    let params = if params.is_empty() {
-        None
-    } else {
-        Some(&*params)
-    };
+    Ok((params, positional(parameters)))
+}
 
-    let mut args = MssqlArguments::default();
+// declarations for the placeholders used in the query, each with a stand-in type, and
+// their count
+fn undescribed_parameters(sql: &str) -> (String, Either<Vec<MssqlTypeInfo>, usize>) {
+    let mut names: Vec<&str> = Vec::new();
 
-    args.declare("", 0_i32);
-    args.add_unnamed(params);
-    args.add_unnamed(sql);
-    args.add_unnamed(0x0001_i32); // 1 = SEND_METADATA
+    for name in placeholders(sql) {
+        if !names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+            names.push(name);
+        }
+    }
+
+    let params = names
+        .iter()
+        .map(|name| format!("{} nvarchar(1)", name))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    (params, Either::Right(names.len()))
+}
+
+// orders the described parameters by their placeholder, @p1, @p2, ...
+// when that is not possible (e.g. a placeholder is never used) only the count is known
+fn positional(parameters: Vec<(String, MssqlTypeInfo)>) -> Either<Vec<MssqlTypeInfo>, usize> {
+    let count = parameters.len();
+    let mut ordered = Vec::with_capacity(count);
+
+    for (name, type_info) in parameters {
+        match name
+            .strip_prefix("@p")
+            .and_then(|n| n.parse::<usize>().ok())
+        {
+            Some(n) if n > 0 => ordered.push((n, type_info)),
+            _ => return Either::Right(count),
+        }
+    }
+
+    ordered.sort_by_key(|(n, _)| *n);
+
+    if ordered.iter().enumerate().all(|(i, (n, _))| *n == i + 1) {
+        Either::Left(ordered.into_iter().map(|(_, ty)| ty).collect())
+    } else {
+        Either::Right(ordered.last().map_or(0, |(n, _)| *n))
+    }
+}
+
+// calls a system procedure on the query and collects the rows it returns
+async fn describe(
+    conn: &mut MssqlConnection,
+    procedure: &str,
+    args: &MssqlArguments,
+) -> Result<Vec<MssqlRow>, Error> {
+    conn.stream.wait_until_ready().await?;
+    conn.cache_prepared_handle();
+    conn.stream.pending_done_count += 1;
 
     conn.stream.write_packet(
         PacketType::Rpc,
         RpcRequest {
             transaction_descriptor: conn.stream.transaction_descriptor,
-            arguments: &args,
-            // [sp_prepare] will emit the column meta data
-            // small issue is that we need to declare all the used placeholders with a "fallback" type
-            // we currently use regex to collect them; false positives are *okay* but false
-            // negatives would break the query
-            procedure: Either::Right(Procedure::Prepare),
+            arguments: args,
+            procedure: Either::Left(procedure),
             options: OptionFlags::empty(),
         },
     );
 
     conn.stream.flush().await?;
-    conn.stream.wait_until_ready().await?;
-    conn.stream.pending_done_count += 1;
 
-    let mut id: Option<i32> = None;
+    let mut rows = Vec::new();
 
     loop {
         let message = conn.stream.recv_message().await?;
 
         match message {
-            Message::DoneProc(done) | Message::Done(done) => {
-                if !done.status.contains(Status::DONE_MORE) {
-                    // done with prepare
-                    conn.stream.handle_done(&done);
-                    break;
-                }
+            Message::Row(row) => {
+                rows.push(MssqlRow {
+                    row,
+                    columns: Arc::clone(&conn.stream.columns),
+                    column_names: Arc::clone(&conn.stream.column_names),
+                });
             }
 
-            Message::ReturnValue(rv) => {
-                id = <i32 as Decode<Mssql>>::decode(MssqlValueRef {
-                    data: rv.value.as_ref(),
-                    type_info: MssqlTypeInfo(rv.type_info),
-                })
-                .ok();
+            Message::DoneProc(done) | Message::Done(done)
+                if !done.status.contains(Status::DONE_MORE) =>
+            {
+                conn.stream.handle_done(&done);
+                break;
             }
 
             _ => {}
         }
     }
 
-    if let Some(id) = id {
-        let mut args = MssqlArguments::default();
-        args.add_unnamed(id);
-
-        conn.stream.write_packet(
-            PacketType::Rpc,
-            RpcRequest {
-                transaction_descriptor: conn.stream.transaction_descriptor,
-                arguments: &args,
-                procedure: Either::Right(Procedure::Unprepare),
-                options: OptionFlags::empty(),
-            },
-        );
+    Ok(rows)
+}
+
+// releases a statement handle from [sp_prepexec] on the server
+pub(crate) async fn unprepare(conn: &mut MssqlConnection, handle: i32) -> Result<(), Error> {
+    let mut args = MssqlArguments::default();
+    args.add_unnamed(handle);
+
+    conn.stream.wait_until_ready().await?;
+    conn.stream.pending_done_count += 1;
 
-        conn.stream.flush().await?;
-        conn.stream.wait_until_ready().await?;
-        conn.stream.pending_done_count += 1;
+    conn.stream.write_packet(
+        PacketType::Rpc,
+        RpcRequest {
+            transaction_descriptor: conn.stream.transaction_descriptor,
+            arguments: &args,
+            procedure: Either::Right(Procedure::Unprepare),
+            options: OptionFlags::empty(),
+        },
+    );
 
-        loop {
-            let message = conn.stream.recv_message().await?;
+    conn.stream.flush().await?;
 
-            match message {
-                Message::DoneProc(done) | Message::Done(done) => {
-                    if !done.status.contains(Status::DONE_MORE) {
-                        // done with unprepare
-                        conn.stream.handle_done(&done);
-                        break;
-                    }
-                }
+    loop {
+        let message = conn.stream.recv_message().await?;
 
-                _ => {}
+        match message {
+            Message::DoneProc(done) | Message::Done(done)
+                if !done.status.contains(Status::DONE_MORE) =>
+            {
+                // done with unprepare
+                conn.stream.handle_done(&done);
+                break;
             }
+
+            _ => {}
         }
     }
 
-    let metadata = Arc::new(MssqlStatementMetadata {
-        columns: conn.stream.columns.as_ref().clone(),
-        column_names: conn.stream.column_names.as_ref().clone(),
-    });
+    Ok(())
+}
 
-    conn.cache_statement.insert(sql, metadata.clone());
+#[test]
+fn test_prepare_undescribed_parameters() {
+    use crate::connection::block_on;
+    use crate::connection::{tabular_result, written_messages};
 
-    Ok(metadata)
+    // ERROR 11508, then DONEPROC with the error flag, for [sp_describe_undeclared_parameters]
+    let message: Vec<u8> = "@p1 is used more than once"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+
+    let mut error = vec![0xf4, 0x2c, 0, 0, 1, 16];
+    error.extend(&((message.len() / 2) as u16).to_le_bytes());
+    error.extend(&message);
+    error.extend(&[0, 0, 1, 0, 0, 0]);
+
+    let mut tokens = vec![0xaa];
+    tokens.extend(&(error.len() as u16).to_le_bytes());
+    tokens.extend(&error);
+    tokens.extend(&[0xfe, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+    let mut response = tabular_result(&tokens);
+
+    // a result without columns for [sp_describe_first_result_set]
+    response.extend(tabular_result(&[0xfe, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]));
+
+    let (mut conn, written) = MssqlConnection::mock(response);
+
+    let metadata = block_on(prepare(&mut conn, "SELECT @p1 + @P1, @p2")).unwrap();
+
+    assert_eq!(metadata.parameters, Some(Either::Right(2)));
+
+    // the placeholders are declared for [sp_describe_first_result_set], and nothing is
+    // prepared until the statement runs
+    let params: Vec<u8> = "@p1 nvarchar(1),@p2 nvarchar(1)"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+
+    let messages = written_messages(&written.lock().unwrap());
+
+    assert_eq!(messages.len(), 2);
+    assert!(messages[1].1.windows(params.len()).any(|w| w == params));
 }
@@ -0,0 +1,165 @@
+// The SQL Server Resolution Protocol (SSRP), spoken with the SQL Server Browser service to
+// find the TCP port a named instance listens on.
+
+use crate::error::Error;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+// the UDP port the SQL Server Browser listens on
+const BROWSER_PORT: u16 = 1434;
+
+// how long to wait for the SQL Server Browser to answer
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+// asks the SQL Server Browser on the host for the TCP port of the named instance
+pub(super) async fn resolve_port(host: &str, instance: &str) -> Result<u16, Error> {
+    let host = host.to_owned();
+    let instance = instance.to_owned();
+
+    sqlx_core::rt::spawn_blocking(move || lookup((&*host, BROWSER_PORT), &instance, TIMEOUT)).await
+}
+
+fn lookup(browser: impl ToSocketAddrs, instance: &str, timeout: Duration) -> Result<u16, Error> {
+    let browser = browser.to_socket_addrs()?.next().ok_or_else(|| {
+        Error::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no address for the SQL Server Browser",
+        ))
+    })?;
+
+    let local: SocketAddr = match browser {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+
+    let socket = UdpSocket::bind(local)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.send_to(&request(instance)?, browser)?;
+
+    // a response to CLNT_UCAST_INST carries at most 1024 bytes of data
+    let mut buf = [0; 1027];
+
+    let len = match socket.recv_from(&mut buf) {
+        Ok((len, _)) => len,
+
+        Err(error)
+            if matches!(
+                error.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "the SQL Server Browser at {} did not answer for the instance {:?}",
+                    browser, instance
+                ),
+            )));
+        }
+
+        Err(error) => return Err(error.into()),
+    };
+
+    parse_port(&buf[..len], instance)
+}
+
+// CLNT_UCAST_INST, asking for the details of a single instance
+fn request(instance: &str) -> Result<Vec<u8>, Error> {
+    if instance.is_empty() || instance.len() > 32 || instance.contains('\0') {
+        return Err(Error::Configuration(
+            format!("invalid SQL Server instance name {:?}", instance).into(),
+        ));
+    }
+
+    let mut buf = Vec::with_capacity(instance.len() + 2);
+
+    buf.push(0x04);
+    buf.extend_from_slice(instance.as_bytes());
+    buf.push(0);
+
+    Ok(buf)
+}
+
+// reads the TCP port from the SVR_RESP answering CLNT_UCAST_INST
+fn parse_port(response: &[u8], instance: &str) -> Result<u16, Error> {
+    let (size, data) = match response {
+        [0x05, lo, hi, data @ ..] => (u16::from_le_bytes([*lo, *hi]) as usize, data),
+
+        _ => return Err(err_protocol!("SSRP: unexpected response")),
+    };
+
+    let data = data
+        .get(..size)
+        .ok_or_else(|| err_protocol!("SSRP: response is shorter than its size"))?;
+
+    // [RESP_DATA] pairs of `name;value;`, ending in `;;`:
+    //   ServerName;HOST;InstanceName;SQLEXPRESS;IsClustered;No;Version;16.0.1000.6;tcp;49172;;
+    let data = String::from_utf8_lossy(data);
+    let mut fields = data.split(';');
+
+    while let Some(name) = fields.next() {
+        let value = fields.next().unwrap_or_default();
+
+        if name.eq_ignore_ascii_case("tcp") {
+            return value
+                .parse()
+                .map_err(|_| err_protocol!("SSRP: invalid TCP port {:?}", value));
+        }
+    }
+
+    Err(Error::Configuration(
+        format!(
+            "the SQL Server instance {:?} does not accept TCP connections",
+            instance
+        )
+        .into(),
+    ))
+}
+
+#[test]
+fn test_lookup() {
+    let browser = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = browser.local_addr().unwrap();
+
+    let stub = std::thread::spawn(move || {
+        let mut buf = [0; 64];
+        let (len, client) = browser.recv_from(&mut buf).unwrap();
+
+        assert_eq!(&buf[..len], b"\x04SQLEXPRESS\0");
+
+        let data = b"ServerName;HOST;InstanceName;SQLEXPRESS;IsClustered;No;\
+                     Version;16.0.1000.6;tcp;49172;np;\\\\HOST\\pipe\\sql\\query;;";
+
+        let mut response = vec![0x05];
+        response.extend(&(data.len() as u16).to_le_bytes());
+        response.extend(data);
+
+        browser.send_to(&response, client).unwrap();
+    });
+
+    let port = lookup(address, "SQLEXPRESS", Duration::from_secs(5)).unwrap();
+    stub.join().unwrap();
+
+    assert_eq!(port, 49172);
+}
+
+#[test]
+fn test_lookup_timeout() {
+    // a browser that never answers
+    let browser = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = browser.local_addr().unwrap();
+
+    let err = lookup(address, "SQLEXPRESS", Duration::from_millis(50)).unwrap_err();
+
+    assert!(matches!(err, Error::Io(error) if error.kind() == io::ErrorKind::TimedOut));
+}
+
+#[test]
+fn test_parse_port() {
+    let response = b"\x05\x29\x00ServerName;HOST;InstanceName;PIPES;np;x;;";
+
+    assert!(parse_port(response, "PIPES").is_err());
+    assert!(parse_port(b"\x05\xff\x00tcp;1433;;", "X").is_err());
+    assert!(request(&"X".repeat(33)).is_err());
+}
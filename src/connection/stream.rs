@@ -1,5 +1,6 @@
 use std::ops::{Deref, DerefMut};
 
+use crate::decode::Decode;
 use crate::error::Error;
 use crate::ext::ustr::UStr;
 use crate::protocol::col_meta_data::ColMetaData;
@@ -10,19 +11,27 @@ use crate::protocol::info::Info;
 use crate::protocol::login_ack::LoginAck;
 use crate::protocol::message::{Message, MessageType};
 use crate::protocol::order::Order;
-use crate::protocol::packet::{PacketHeader, PacketType, Status};
+use crate::protocol::packet::{PacketHeader, PacketType, Packets, Status};
 use crate::protocol::return_status::ReturnStatus;
 use crate::protocol::return_value::ReturnValue;
 use crate::protocol::row::Row;
 use crate::HashMap;
-use crate::{MssqlColumn, MssqlConnectOptions, MssqlDatabaseError};
+use crate::{
+    Mssql, MssqlColumn, MssqlConnectOptions, MssqlDatabaseError, MssqlInfo, MssqlTypeInfo,
+    MssqlValueRef,
+};
 use bytes::{Bytes, BytesMut};
+use log::LevelFilter;
 use sqlx_core::io::Encode;
+use sqlx_core::logger::private_level_filter_to_levels;
 use std::sync::Arc;
 
-use crate::connection::tls::MaybeUpgradeTls;
+use crate::connection::ssrp;
+use crate::connection::tls::{self, MaybeUpgradeTls, SharedSocket};
 use crate::net::{self, BufferedSocket, Socket};
 
+pub(crate) type InfoHandler = Box<dyn FnMut(MssqlInfo) + Send>;
+
 pub(crate) struct MssqlStream {
     // A trait object is okay here as the buffering amortizes the overhead of both the dynamic
     // function call as well as the syscall.
@@ -31,11 +40,30 @@ pub(crate) struct MssqlStream {
     // how many Done (or Error) we are currently waiting for
     pub(crate) pending_done_count: usize,
 
+    // set when a message was left unfinished, as by a bulk insert dropped in the middle of
+    // a batch; nothing else can be sent on the connection after that
+    pub(crate) broken: bool,
+
     // current transaction descriptor
     // set from ENVCHANGE on `BEGIN` and reset to `0` on a ROLLBACK
     pub(crate) transaction_descriptor: u64,
     pub(crate) transaction_depth: usize,
 
+    // the size outgoing messages are chunked into, packet header included
+    // requested in LOGIN7 and confirmed by the server through ENVCHANGE
+    packet_size: usize,
+
+    // INFO messages (PRINT, low severity RAISERROR) are logged at this level and
+    // then handed to the handler, if one is set
+    pub(crate) info_log_level: LevelFilter,
+    pub(crate) info_handler: Option<InfoHandler>,
+
+    // the statement cache key of a statement that the request in flight prepares; its
+    // handle arrives as a RETURNVALUE after the results, possibly while they are drained
+    pub(crate) pending_prepare: Option<String>,
+    // the handle that arrived for [pending_prepare], to be put into the statement cache
+    pub(crate) prepared: Option<(String, i32)>,
+
     // current TabularResult from the server that we are iterating over
     response: Option<(PacketHeader, Bytes)>,
 
@@ -47,50 +75,108 @@ pub(crate) struct MssqlStream {
 
 impl MssqlStream {
     pub(super) async fn connect(options: &MssqlConnectOptions) -> Result<Self, Error> {
-        let socket_future =
-            net::connect_tcp(&options.host, options.port, MaybeUpgradeTls(options)).await?;
+        let port = match &options.instance_name {
+            Some(instance) if !options.port_set => {
+                ssrp::resolve_port(&options.host, instance).await?
+            }
+
+            _ => options.port,
+        };
+
+        let socket_future = net::connect_tcp(&options.host, port, MaybeUpgradeTls(options)).await?;
 
         let socket = socket_future.await?;
 
-        Ok(Self {
+        Ok(Self::new(socket))
+    }
+
+    fn new(socket: Box<dyn Socket>) -> Self {
+        Self {
             inner: BufferedSocket::new(socket),
             columns: Default::default(),
             column_names: Default::default(),
             response: None,
             pending_done_count: 0,
+            broken: false,
             transaction_descriptor: 0,
             transaction_depth: 0,
-        })
+            packet_size: 4096,
+            info_log_level: LevelFilter::Debug,
+            info_handler: None,
+            pending_prepare: None,
+            prepared: None,
+        }
     }
 
-    // writes the packet out to the write buffer
-    // will (eventually) handle packet chunking
-    pub(crate) fn write_packet<'en, T: Encode<'en>>(&mut self, ty: PacketType, payload: T) {
-        // TODO: Support packet chunking for large packet sizes
-        //       We likely need to double-buffer the writes so we know to chunk
+    // switches the connection over to TLS
+    // must happen directly after the PRELOGIN exchange, while nothing else is in flight
+    pub(super) async fn upgrade(self, options: &MssqlConnectOptions) -> Result<Self, Error> {
+        let socket = tls::upgrade(self.inner.into_inner(), options).await?;
+
+        Ok(Self::new(socket))
+    }
 
-        // write out the packet header, leaving room for setting the packet length later
+    // switches the connection over to TLS for the LOGIN7 packet only; the returned socket
+    // is handed to [downgrade] once that packet is out
+    pub(super) async fn upgrade_for_login(
+        self,
+        options: &MssqlConnectOptions,
+    ) -> Result<(Self, SharedSocket), Error> {
+        let socket = SharedSocket::new(self.inner.into_inner());
+        let tls_socket = tls::upgrade(socket.clone(), options).await?;
 
-        //find the length of the payload
-        let mut buf = Vec::default();
+        Ok((Self::new(tls_socket), socket))
+    }
+
+    // drops the TLS stream and carries on in plain text on the socket underneath
+    pub(super) fn downgrade(self, socket: SharedSocket) -> Result<Self, Error> {
+        if !self.inner.write_buffer().is_empty() {
+            return Err(err_protocol!(
+                "LOGIN7 must be flushed before leaving the TLS stream"
+            ));
+        }
+
+        drop(self);
+
+        Ok(Self::new(socket.into_inner()?))
+    }
+
+    // writes the message out to the write buffer, chunked into as many packets as needed
+    pub(crate) fn write_packet<'en, T: Encode<'en>>(&mut self, ty: PacketType, payload: T) {
+        // the message is double-buffered as we need to know its size to chunk it
+        let mut buf = Vec::new();
         payload.encode_with(&mut buf, ());
-        let data_size = buf.len();
-        let header_size = 8;
-        let len = (data_size + header_size) as u16;
 
-        let header_packet = PacketHeader {
+        self.inner.write(Packets {
             r#type: ty,
-            status: Status::END_OF_MESSAGE,
-            length: len,
-            server_process_id: 0,
-            packet_id: 1,
+            payload: &buf,
+            packet_size: self.packet_size,
+            end_of_message: true,
+        });
+    }
+
+    // writes out as much of a message that is still being produced as fills whole packets,
+    // removing it from the buffer; with [end] set, the rest of it is written and ends the message
+    pub(crate) fn write_message_part(&mut self, ty: PacketType, buf: &mut Vec<u8>, end: bool) {
+        let len = if end {
+            buf.len()
+        } else {
+            let max_data_size = self.packet_size - PacketHeader::SIZE;
+            buf.len() - buf.len() % max_data_size
         };
 
-        //write the header
-        let mut len_offset = 0;
-        self.inner.write_with(header_packet, &mut len_offset);
-        //write the packet
-        self.inner.write(payload);
+        if len == 0 && !end {
+            return;
+        }
+
+        self.inner.write(Packets {
+            r#type: ty,
+            payload: &buf[..len],
+            packet_size: self.packet_size,
+            end_of_message: end,
+        });
+
+        buf.drain(..len);
     }
 
     // receive the next packet from the database
@@ -107,11 +193,12 @@ impl MssqlStream {
             ));
         }
 
-        let mut payload: BytesMut;
+        let mut payload = BytesMut::new();
 
+        // a message, such as a row with a large MAX value, can span several packets
         loop {
             let len = (header.length - 8) as usize;
-            payload = self.inner.read_buffered(len).await?;
+            payload.unsplit(self.inner.read_buffered(len).await?);
 
             if header.status.contains(Status::END_OF_MESSAGE) {
                 break;
@@ -125,9 +212,9 @@ impl MssqlStream {
 
     // receive the next ~message~
     // TDS communicates in streams of packets that are themselves streams of messages
-    pub(super) async fn recv_message(&mut self) -> Result<Message, Error> {
+    pub(crate) async fn recv_message(&mut self) -> Result<Message, Error> {
         loop {
-            while self.response.as_ref().map_or(false, |r| !r.1.is_empty()) {
+            while self.response.as_ref().is_some_and(|r| !r.1.is_empty()) {
                 let buf = if let Some((_, buf)) = self.response.as_mut() {
                     buf
                 } else {
@@ -149,6 +236,17 @@ impl MssqlStream {
                                 self.transaction_descriptor = 0;
                             }
 
+                            EnvChange::PacketSize(size) => {
+                                // the packet size the server settled on during login
+                                self.packet_size = size
+                                    .parse()
+                                    .ok()
+                                    .filter(|size| (512..=32767).contains(size))
+                                    .ok_or_else(|| {
+                                        err_protocol!("ENVCHANGE: invalid packet size {:?}", size)
+                                    })?;
+                            }
+
                             _ => {}
                         }
 
@@ -156,7 +254,8 @@ impl MssqlStream {
                     }
 
                     MessageType::Info => {
-                        let _ = Info::get(buf)?;
+                        let info = Info::get(buf)?;
+                        self.handle_info(info);
                         continue;
                     }
 
@@ -164,11 +263,32 @@ impl MssqlStream {
                     MessageType::NbcRow => Message::Row(Row::get(buf, true, &self.columns)?),
                     MessageType::LoginAck => Message::LoginAck(LoginAck::get(buf)?),
                     MessageType::ReturnStatus => Message::ReturnStatus(ReturnStatus::get(buf)?),
-                    MessageType::ReturnValue => Message::ReturnValue(ReturnValue::get(buf)?),
+                    MessageType::ReturnValue => {
+                        let return_value = ReturnValue::get(buf)?;
+
+                        // the handle is the first OUTPUT parameter of [sp_prepexec]
+                        if let Some(key) = self.pending_prepare.take() {
+                            let handle = <i32 as Decode<Mssql>>::decode(MssqlValueRef {
+                                data: return_value.value.as_ref(),
+                                type_info: MssqlTypeInfo(return_value.type_info.clone()),
+                            });
+
+                            if let Ok(handle) = handle {
+                                self.prepared = Some((key, handle));
+                            }
+                        }
+
+                        Message::ReturnValue(return_value)
+                    }
                     MessageType::Done => Message::Done(Done::get(buf)?),
                     MessageType::DoneInProc => Message::DoneInProc(Done::get(buf)?),
                     MessageType::DoneProc => Message::DoneProc(Done::get(buf)?),
-                    MessageType::Order => Message::Order(Order::get(buf)?),
+
+                    MessageType::Order => {
+                        // NOTE: nothing needs the columns a result is ordered by
+                        Order::get(buf)?;
+                        continue;
+                    }
 
                     MessageType::Error => {
                         let error = ProtocolError::get(buf)?;
@@ -197,6 +317,32 @@ impl MssqlStream {
 
     pub(crate) fn handle_done(&mut self, _done: &Done) {
         self.pending_done_count -= 1;
+
+        if self.pending_done_count == 0 {
+            // a statement that was to be prepared, but whose handle never came
+            self.pending_prepare = None;
+        }
+    }
+
+    fn handle_info(&mut self, info: Info) {
+        if let Some((level, _)) = private_level_filter_to_levels(self.info_log_level) {
+            sqlx_core::private_tracing_dynamic_event!(
+                target: "sqlx::mssql::info",
+                level,
+                number = info.number,
+                state = info.state,
+                class = info.class,
+                server = %info.server,
+                procedure = %info.procedure,
+                line = info.line,
+                "{}",
+                info.message
+            );
+        }
+
+        if let Some(handler) = &mut self.info_handler {
+            handler(MssqlInfo(info));
+        }
     }
 
     pub(crate) fn handle_error<T>(&mut self, error: ProtocolError) -> Result<T, Error> {
@@ -205,6 +351,12 @@ impl MssqlStream {
     }
 
     pub(crate) async fn wait_until_ready(&mut self) -> Result<(), Error> {
+        if self.broken {
+            return Err(err_protocol!(
+                "the connection was left in the middle of a message and can no longer be used"
+            ));
+        }
+
         self.inner.flush().await?;
 
         while self.pending_done_count > 0 {
@@ -237,3 +389,203 @@ impl DerefMut for MssqlStream {
         &mut self.inner
     }
 }
+
+// an in-memory socket that answers with canned server responses and keeps what is written
+#[cfg(test)]
+pub(crate) struct MockSocket {
+    response: Vec<u8>,
+    written: Arc<std::sync::Mutex<Vec<u8>>>,
+}
+
+#[cfg(test)]
+impl Socket for MockSocket {
+    fn try_read(&mut self, buf: &mut dyn sqlx_core::io::ReadBuf) -> std::io::Result<usize> {
+        let len = buf.remaining_mut().min(self.response.len());
+
+        buf.put_slice(&self.response[..len]);
+        self.response.drain(..len);
+
+        Ok(len)
+    }
+
+    fn try_write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.written.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn poll_read_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_write_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+impl MssqlStream {
+    // a stream reading the given server responses, along with what gets written to it
+    pub(crate) fn mock(response: Vec<u8>) -> (Self, Arc<std::sync::Mutex<Vec<u8>>>) {
+        let written = Arc::default();
+
+        let socket = MockSocket {
+            response,
+            written: Arc::clone(&written),
+        };
+
+        (Self::new(Box::new(socket)), written)
+    }
+}
+
+// wraps the tokens of a server response into a TabularResult packet
+#[cfg(test)]
+pub(crate) fn tabular_result(tokens: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0x04, 0x01];
+    packet.extend(&(tokens.len() as u16 + 8).to_be_bytes());
+    packet.extend(&[0, 0, 1, 0]);
+    packet.extend(tokens);
+    packet
+}
+
+// the messages written by the client, as their packet type and payload
+#[cfg(test)]
+pub(crate) fn written_messages(mut written: &[u8]) -> Vec<(u8, Vec<u8>)> {
+    let mut messages = Vec::new();
+    let mut payload = Vec::new();
+
+    while written.len() >= PacketHeader::SIZE {
+        let len = u16::from_be_bytes([written[2], written[3]]) as usize;
+
+        payload.extend(&written[PacketHeader::SIZE..len]);
+
+        if written[1] & 0x01 != 0 {
+            messages.push((written[0], std::mem::take(&mut payload)));
+        }
+
+        written = &written[len..];
+    }
+
+    messages
+}
+
+// an ENVCHANGE of type 4 (packet size) from 4096, followed by DONE
+#[cfg(test)]
+fn packet_size_change(size: &str) -> Vec<u8> {
+    let new: Vec<u8> = size.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let old: Vec<u8> = "4096".encode_utf16().flat_map(u16::to_le_bytes).collect();
+
+    let mut tokens = vec![0xe3];
+    tokens.extend(&((3 + new.len() + old.len()) as u16).to_le_bytes());
+    tokens.extend(&[0x04, size.len() as u8]);
+    tokens.extend(&new);
+    tokens.push(4);
+    tokens.extend(&old);
+    tokens.extend(&[0xfd, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+    tabular_result(&tokens)
+}
+
+#[test]
+fn test_packet_size_from_env_change() {
+    let (mut stream, _) = MssqlStream::mock(packet_size_change("8192"));
+
+    let message = crate::connection::block_on(stream.recv_message()).unwrap();
+
+    assert!(matches!(message, Message::Done(_)));
+    assert_eq!(stream.packet_size, 8192);
+
+    for size in ["0", "511", "32768", "65536", "-1", "x"] {
+        let (mut stream, _) = MssqlStream::mock(packet_size_change(size));
+
+        assert!(crate::connection::block_on(stream.recv_message()).is_err());
+    }
+}
+
+// an INFO token with the message, as sent for a `PRINT`, followed by DONE
+#[cfg(test)]
+fn print(message: &str) -> Vec<u8> {
+    let utf16: Vec<u8> = message.encode_utf16().flat_map(u16::to_le_bytes).collect();
+
+    let mut info = vec![0, 0, 0, 0, 1, 0];
+    info.extend(&((utf16.len() / 2) as u16).to_le_bytes());
+    info.extend(&utf16);
+    info.extend(&[0, 0, 1, 0, 0, 0]);
+
+    let mut tokens = vec![0xab];
+    tokens.extend(&(info.len() as u16).to_le_bytes());
+    tokens.extend(&info);
+    tokens.extend(&[0xfd, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+    tabular_result(&tokens)
+}
+
+#[test]
+fn test_handle_info() {
+    // collects the messages logged for INFO tokens
+    struct InfoLogger(std::sync::Mutex<Vec<(log::Level, String)>>);
+
+    impl log::Log for InfoLogger {
+        fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+            metadata.target() == "sqlx::mssql::info"
+        }
+
+        fn log(&self, record: &log::Record<'_>) {
+            if self.enabled(record.metadata()) {
+                let message = record.args().to_string();
+                self.0.lock().unwrap().push((record.level(), message));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: InfoLogger = InfoLogger(std::sync::Mutex::new(Vec::new()));
+
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(LevelFilter::Trace);
+
+    let logged = |text: &str| -> Vec<log::Level> {
+        let logged = LOGGER.0.lock().unwrap();
+
+        logged
+            .iter()
+            .filter(|(_, message)| message.contains(text))
+            .map(|(level, _)| *level)
+            .collect()
+    };
+
+    // without a handler, the message is only logged
+    let (mut stream, _) = MssqlStream::mock(print("no handler"));
+    stream.info_log_level = LevelFilter::Warn;
+
+    crate::connection::block_on(stream.recv_message()).unwrap();
+
+    assert_eq!(logged("no handler"), [log::Level::Warn]);
+
+    // a handler is called in addition to logging, which can be turned off
+    let received = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let (mut stream, _) = MssqlStream::mock(print("with handler"));
+    stream.info_log_level = LevelFilter::Off;
+    stream.info_handler = Some(Box::new({
+        let received = Arc::clone(&received);
+        move |info| received.lock().unwrap().push(info.message().to_owned())
+    }));
+
+    crate::connection::block_on(stream.recv_message()).unwrap();
+
+    assert_eq!(*received.lock().unwrap(), ["with handler"]);
+    assert!(logged("with handler").is_empty());
+}
@@ -0,0 +1,404 @@
+// The TLS handshake of TDS 8.0 strict encryption, made directly on the TCP socket.
+//
+// TDS 8.0 asks the client to offer the ALPN protocol `tds/8.0`, which sqlx-core's
+// `TlsConfig` has no way to do, so the TLS backend is configured here instead. The
+// server certificate is always verified, host name included, as with `VerifyFull`.
+
+use crate::error::Error;
+use crate::net::Socket;
+use crate::MssqlConnectOptions;
+
+#[cfg(any(feature = "tls-native-tls", feature = "tls-rustls"))]
+// the ALPN protocol identifier of TDS 8.0
+const ALPN_PROTOCOL: &str = "tds/8.0";
+
+#[cfg(feature = "tls-native-tls")]
+pub(super) async fn handshake<S: Socket>(
+    socket: S,
+    options: &MssqlConnectOptions,
+) -> Result<Box<dyn Socket>, Error> {
+    Ok(Box::new(tls_native_tls::handshake(socket, options).await?))
+}
+
+#[cfg(all(feature = "tls-rustls", not(feature = "tls-native-tls")))]
+pub(super) async fn handshake<S: Socket>(
+    socket: S,
+    options: &MssqlConnectOptions,
+) -> Result<Box<dyn Socket>, Error> {
+    Ok(Box::new(tls_rustls::handshake(socket, options).await?))
+}
+
+#[cfg(not(any(feature = "tls-native-tls", feature = "tls-rustls")))]
+pub(super) async fn handshake<S: Socket>(
+    socket: S,
+    options: &MssqlConnectOptions,
+) -> Result<Box<dyn Socket>, Error> {
+    drop((socket, options));
+
+    Err(Error::Tls(
+        "`ssl_mode` is set to `strict` but this crate was built without a TLS backend; \
+         enable the `tls-rustls` or `tls-native-tls` feature"
+            .into(),
+    ))
+}
+
+#[cfg(any(feature = "tls-native-tls", feature = "tls-rustls"))]
+mod util {
+    use std::io::{self, Read, Write};
+    use std::task::{Context, Poll};
+
+    use futures_core::ready;
+    use sqlx_core::net::tls::CertificateInput;
+
+    use crate::error::Error;
+    use crate::net::Socket;
+
+    pub(super) async fn certificate_data(certificate: &CertificateInput) -> Result<Vec<u8>, Error> {
+        Ok(match certificate {
+            CertificateInput::Inline(data) => data.clone(),
+            CertificateInput::File(path) => sqlx_core::fs::read(path).await?,
+        })
+    }
+
+    // Blocking `Read` and `Write` on top of a non-blocking socket, for the TLS backends.
+    // An operation that would block remembers to wait for the socket to become ready.
+    pub(super) struct StdSocket<S> {
+        pub(super) socket: S,
+        wants_read: bool,
+        wants_write: bool,
+    }
+
+    impl<S: Socket> StdSocket<S> {
+        pub(super) fn new(socket: S) -> Self {
+            Self {
+                socket,
+                wants_read: false,
+                wants_write: false,
+            }
+        }
+
+        pub(super) fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            if self.wants_write {
+                ready!(self.socket.poll_write_ready(cx))?;
+                self.wants_write = false;
+            }
+
+            if self.wants_read {
+                ready!(self.socket.poll_read_ready(cx))?;
+                self.wants_read = false;
+            }
+
+            Poll::Ready(Ok(()))
+        }
+
+        #[cfg(feature = "tls-native-tls")]
+        pub(super) async fn ready(&mut self) -> io::Result<()> {
+            futures_util::future::poll_fn(|cx| self.poll_ready(cx)).await
+        }
+    }
+
+    impl<S: Socket> Read for StdSocket<S> {
+        fn read(&mut self, mut buf: &mut [u8]) -> io::Result<usize> {
+            self.wants_read = true;
+            let read = self.socket.try_read(&mut buf)?;
+            self.wants_read = false;
+
+            Ok(read)
+        }
+    }
+
+    impl<S: Socket> Write for StdSocket<S> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.wants_write = true;
+            let written = self.socket.try_write(buf)?;
+            self.wants_write = false;
+
+            Ok(written)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(feature = "tls-rustls", not(feature = "tls-native-tls")))]
+mod tls_rustls {
+    use std::io::{self, Cursor, Read, Write};
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+
+    use futures_util::future;
+    use rustls::{
+        Certificate, ClientConfig, ClientConnection, OwnedTrustAnchor, PrivateKey, RootCertStore,
+        ServerName,
+    };
+    use sqlx_core::io::ReadBuf;
+
+    use super::util::{certificate_data, StdSocket};
+    use super::ALPN_PROTOCOL;
+    use crate::error::Error;
+    use crate::net::Socket;
+    use crate::MssqlConnectOptions;
+
+    pub(super) struct RustlsSocket<S> {
+        inner: StdSocket<S>,
+        state: ClientConnection,
+        close_notify_sent: bool,
+    }
+
+    impl<S: Socket> RustlsSocket<S> {
+        fn poll_complete_io(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            loop {
+                match self.state.complete_io(&mut self.inner) {
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        futures_util::ready!(self.inner.poll_ready(cx))?;
+                    }
+
+                    ready => return Poll::Ready(ready.map(|_| ())),
+                }
+            }
+        }
+    }
+
+    impl<S: Socket> Socket for RustlsSocket<S> {
+        fn try_read(&mut self, buf: &mut dyn ReadBuf) -> io::Result<usize> {
+            self.state.reader().read(buf.init_mut())
+        }
+
+        fn try_write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            match self.state.writer().write(buf) {
+                // a zero-length write means the buffer is full
+                Ok(0) => Err(io::ErrorKind::WouldBlock.into()),
+                other => other,
+            }
+        }
+
+        fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.poll_complete_io(cx)
+        }
+
+        fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.poll_complete_io(cx)
+        }
+
+        fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.poll_complete_io(cx)
+        }
+
+        fn poll_shutdown(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            if !self.close_notify_sent {
+                self.state.send_close_notify();
+                self.close_notify_sent = true;
+            }
+
+            futures_util::ready!(self.poll_complete_io(cx))?;
+            self.inner.socket.poll_shutdown(cx)
+        }
+    }
+
+    pub(super) async fn handshake<S: Socket>(
+        socket: S,
+        options: &MssqlConnectOptions,
+    ) -> Result<RustlsSocket<S>, Error> {
+        let config = client_config(options).await?;
+        let host =
+            ServerName::try_from(options.certificate_host()).map_err(|e| Error::Tls(e.into()))?;
+
+        let mut socket = RustlsSocket {
+            inner: StdSocket::new(socket),
+            state: ClientConnection::new(Arc::new(config), host)
+                .map_err(|e| Error::Tls(e.into()))?,
+            close_notify_sent: false,
+        };
+
+        future::poll_fn(|cx| socket.poll_complete_io(cx)).await?;
+
+        Ok(socket)
+    }
+
+    pub(super) async fn client_config(
+        options: &MssqlConnectOptions,
+    ) -> Result<ClientConfig, Error> {
+        let mut roots = RootCertStore::empty();
+
+        roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+            OwnedTrustAnchor::from_subject_spki_name_constraints(
+                ta.subject,
+                ta.spki,
+                ta.name_constraints,
+            )
+        }));
+
+        if let Some(ca) = &options.ssl_root_cert {
+            let data = certificate_data(ca).await?;
+
+            for cert in rustls_pemfile::certs(&mut Cursor::new(data))
+                .map_err(|_| Error::Tls(format!("invalid certificate {}", ca).into()))?
+            {
+                roots
+                    .add(&Certificate(cert))
+                    .map_err(|e| Error::Tls(e.into()))?;
+            }
+        }
+
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots);
+
+        let mut config = match (&options.ssl_client_cert, &options.ssl_client_key) {
+            (Some(cert), Some(key)) => {
+                let certs = rustls_pemfile::certs(&mut Cursor::new(certificate_data(cert).await?))?
+                    .into_iter()
+                    .map(Certificate)
+                    .collect();
+
+                let key = private_key(certificate_data(key).await?)?;
+
+                config
+                    .with_client_auth_cert(certs, key)
+                    .map_err(|e| Error::Tls(e.into()))?
+            }
+
+            (None, None) => config.with_no_client_auth(),
+
+            _ => {
+                return Err(Error::Configuration(
+                    "`ssl_client_cert` and `ssl_client_key` must be given together".into(),
+                ));
+            }
+        };
+
+        config.alpn_protocols = vec![ALPN_PROTOCOL.as_bytes().to_vec()];
+
+        Ok(config)
+    }
+
+    fn private_key(pem: Vec<u8>) -> Result<PrivateKey, Error> {
+        let mut reader = Cursor::new(pem);
+
+        while let Some(item) = rustls_pemfile::read_one(&mut reader)? {
+            if let rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) = item
+            {
+                return Ok(PrivateKey(key));
+            }
+        }
+
+        Err(Error::Configuration(
+            "no private key in `ssl_client_key`".into(),
+        ))
+    }
+}
+
+#[cfg(feature = "tls-native-tls")]
+mod tls_native_tls {
+    use std::io::{self, Read, Write};
+    use std::task::{Context, Poll};
+
+    use native_tls::{Certificate, HandshakeError, Identity, TlsConnector, TlsStream};
+    use sqlx_core::io::ReadBuf;
+
+    use super::util::{certificate_data, StdSocket};
+    use super::ALPN_PROTOCOL;
+    use crate::error::Error;
+    use crate::net::Socket;
+    use crate::MssqlConnectOptions;
+
+    pub(super) struct NativeTlsSocket<S: Socket> {
+        stream: TlsStream<StdSocket<S>>,
+    }
+
+    impl<S: Socket> Socket for NativeTlsSocket<S> {
+        fn try_read(&mut self, buf: &mut dyn ReadBuf) -> io::Result<usize> {
+            self.stream.read(buf.init_mut())
+        }
+
+        fn try_write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.stream.write(buf)
+        }
+
+        fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.stream.get_mut().poll_ready(cx)
+        }
+
+        fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.stream.get_mut().poll_ready(cx)
+        }
+
+        fn poll_shutdown(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match self.stream.shutdown() {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    self.stream.get_mut().poll_ready(cx)
+                }
+
+                ready => Poll::Ready(ready),
+            }
+        }
+    }
+
+    pub(super) async fn handshake<S: Socket>(
+        socket: S,
+        options: &MssqlConnectOptions,
+    ) -> Result<NativeTlsSocket<S>, Error> {
+        let mut builder = TlsConnector::builder();
+
+        builder.request_alpns(&[ALPN_PROTOCOL]);
+
+        if let Some(ca) = &options.ssl_root_cert {
+            let data = certificate_data(ca).await?;
+            builder.add_root_certificate(
+                Certificate::from_pem(&data).map_err(|e| Error::Tls(e.into()))?,
+            );
+        }
+
+        match (&options.ssl_client_cert, &options.ssl_client_key) {
+            (Some(cert), Some(key)) => {
+                let cert = certificate_data(cert).await?;
+                let key = certificate_data(key).await?;
+
+                builder
+                    .identity(Identity::from_pkcs8(&cert, &key).map_err(|e| Error::Tls(e.into()))?);
+            }
+
+            (None, None) => {}
+
+            _ => {
+                return Err(Error::Configuration(
+                    "`ssl_client_cert` and `ssl_client_key` must be given together".into(),
+                ));
+            }
+        }
+
+        let connector = builder.build().map_err(|e| Error::Tls(e.into()))?;
+
+        let mut mid_handshake =
+            match connector.connect(options.certificate_host(), StdSocket::new(socket)) {
+                Ok(stream) => return Ok(NativeTlsSocket { stream }),
+                Err(HandshakeError::Failure(e)) => return Err(Error::Tls(e.into())),
+                Err(HandshakeError::WouldBlock(mid_handshake)) => mid_handshake,
+            };
+
+        loop {
+            mid_handshake.get_mut().ready().await?;
+
+            match mid_handshake.handshake() {
+                Ok(stream) => return Ok(NativeTlsSocket { stream }),
+                Err(HandshakeError::Failure(e)) => return Err(Error::Tls(e.into())),
+                Err(HandshakeError::WouldBlock(next)) => mid_handshake = next,
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "tls-rustls", not(feature = "tls-native-tls")))]
+#[test]
+fn test_rustls_offers_alpn() {
+    let config = crate::connection::block_on(tls_rustls::client_config(
+        &MssqlConnectOptions::new().host("db.example.com"),
+    ))
+    .unwrap();
+
+    assert_eq!(config.alpn_protocols, [b"tds/8.0".to_vec()]);
+}
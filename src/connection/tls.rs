@@ -1,10 +1,135 @@
-use futures_core::future::BoxFuture;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll};
+
+use futures_core::ready;
 
 use crate::error::Error;
 use crate::net::tls::{self, TlsConfig};
 use crate::net::{Socket, SocketIntoBox, WithSocket};
-
+use crate::protocol::packet::{PacketHeader, PacketType, Status};
+use crate::protocol::pre_login::Encrypt;
 use crate::{MssqlConnectOptions, MssqlSslMode};
+use futures_core::future::BoxFuture;
+use sqlx_core::io::{Encode, ReadBuf};
+
+// the packet size in effect until LOGIN7 negotiates a different one
+const PRELOGIN_PACKET_SIZE: usize = 4096;
+
+/// The encryption negotiated with the server during PRELOGIN.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MssqlEncryption {
+    /// Nothing is encrypted, not even the credentials.
+    Off,
+
+    /// Only the LOGIN7 packet, which carries the credentials, is encrypted; the rest of
+    /// the session is sent in plain text.
+    LoginOnly,
+
+    /// The whole session is encrypted.
+    On,
+
+    /// The whole session, PRELOGIN included, is sent through a TLS stream set up as
+    /// soon as the socket connected (TDS 8.0).
+    Strict,
+}
+
+impl MssqlSslMode {
+    // the ENCRYPTION option we send in our PRELOGIN message
+    pub(crate) fn requested_encryption(self, tls_available: bool) -> Encrypt {
+        match self {
+            MssqlSslMode::Disable => Encrypt::NOT_SUPPORTED,
+
+            // without a TLS backend there is nothing to offer; only the modes that
+            // require TLS go on to fail
+            MssqlSslMode::Allow | MssqlSslMode::Prefer if !tls_available => Encrypt::NOT_SUPPORTED,
+
+            // leave it to the server whether to encrypt more than the login
+            MssqlSslMode::Allow => Encrypt::OFF,
+
+            // under TDS 8.0 the server disregards this option
+            MssqlSslMode::Prefer
+            | MssqlSslMode::Require
+            | MssqlSslMode::VerifyCa
+            | MssqlSslMode::VerifyFull
+            | MssqlSslMode::Strict => Encrypt::ON,
+        }
+    }
+
+    // decides how the connection is to be encrypted given the ENCRYPTION option the
+    // server answered our PRELOGIN with
+    pub(crate) fn negotiate(
+        self,
+        server: Encrypt,
+        tls_available: bool,
+    ) -> Result<MssqlEncryption, Error> {
+        if matches!(self, MssqlSslMode::Strict) {
+            // already inside the TLS stream
+            return Ok(MssqlEncryption::Strict);
+        }
+
+        let server = server - Encrypt::CLIENT_CERT;
+
+        if !tls_available
+            && (server == Encrypt::ON
+                || server == Encrypt::REQUIRED
+                || matches!(
+                    self,
+                    MssqlSslMode::Require | MssqlSslMode::VerifyCa | MssqlSslMode::VerifyFull
+                ))
+        {
+            return Err(Error::Tls(
+                "TLS is required but this crate was built without a TLS backend; \
+                 enable the `tls-rustls` or `tls-native-tls` feature"
+                    .into(),
+            ));
+        }
+
+        if server == Encrypt::ON || server == Encrypt::REQUIRED {
+            if matches!(self, MssqlSslMode::Disable) {
+                return Err(Error::Tls(
+                    "server requires encryption but `ssl_mode` is set to `disable`".into(),
+                ));
+            }
+
+            return Ok(MssqlEncryption::On);
+        }
+
+        if matches!(
+            self,
+            MssqlSslMode::Require | MssqlSslMode::VerifyCa | MssqlSslMode::VerifyFull
+        ) {
+            return Err(Error::Tls(
+                if server == Encrypt::OFF {
+                    "server only supports encryption of the login packet"
+                } else {
+                    "server does not support TLS"
+                }
+                .into(),
+            ));
+        }
+
+        if server == Encrypt::OFF && !matches!(self, MssqlSslMode::Disable) {
+            // without a TLS backend we asked for no encryption at all, which is fine
+            // by a server that does not require it
+            return Ok(if tls_available {
+                MssqlEncryption::LoginOnly
+            } else {
+                MssqlEncryption::Off
+            });
+        }
+
+        if server == Encrypt::NOT_SUPPORTED {
+            return Ok(MssqlEncryption::Off);
+        }
+
+        Err(err_protocol!(
+            "PRELOGIN: unexpected encryption option 0x{:02x}",
+            server.bits()
+        ))
+    }
+}
 
 pub struct MaybeUpgradeTls<'a>(pub &'a MssqlConnectOptions);
 
@@ -16,10 +141,439 @@ impl<'a> WithSocket for MaybeUpgradeTls<'a> {
     }
 }
 
+// In strict mode, TLS comes first and everything after it, PRELOGIN included, goes
+// through the TLS stream. Otherwise the socket is left as is for PRELOGIN to decide.
 async fn maybe_upgrade<S: Socket>(
-    mut socket: S,
+    socket: S,
     options: &MssqlConnectOptions,
 ) -> Result<Box<dyn Socket>, Error> {
-    // TODO: make ssl upgrades work
-    Ok(Box::new(socket))
+    if !matches!(options.ssl_mode, MssqlSslMode::Strict) {
+        return Ok(Box::new(socket));
+    }
+
+    super::strict::handshake(socket, options).await
+}
+
+// Upgrades the connection to TLS after the PRELOGIN exchange.
+//
+// The TLS handshake itself is not sent on the bare socket but is wrapped in TDS
+// packets of type PRELOGIN. Once the handshake completes the TLS records flow on the
+// socket as-is, with the TDS packets carried as their payload.
+pub(super) async fn upgrade<S: Socket>(
+    socket: S,
+    options: &MssqlConnectOptions,
+) -> Result<Box<dyn Socket>, Error> {
+    let handshake_complete = Arc::new(AtomicBool::new(false));
+    let socket = TlsPreloginWrapper::new(socket, Arc::clone(&handshake_complete));
+
+    let socket = tls::handshake(socket, tls_config(options), SocketIntoBox).await?;
+
+    handshake_complete.store(true, Ordering::Release);
+
+    Ok(socket)
+}
+
+fn tls_config(options: &MssqlConnectOptions) -> TlsConfig<'_> {
+    let accept_invalid_certs = match options.ssl_mode {
+        MssqlSslMode::VerifyCa | MssqlSslMode::VerifyFull | MssqlSslMode::Strict => false,
+
+        // with a root certificate, `require` verifies the same as `verify-ca`
+        MssqlSslMode::Require => options.ssl_root_cert.is_none(),
+
+        _ => true,
+    };
+
+    let accept_invalid_hostnames = !matches!(
+        options.ssl_mode,
+        MssqlSslMode::VerifyFull | MssqlSslMode::Strict
+    );
+
+    TlsConfig {
+        accept_invalid_certs,
+        accept_invalid_hostnames,
+        hostname: options.certificate_host(),
+        root_cert_path: options.ssl_root_cert.as_ref(),
+        client_cert_path: options.ssl_client_cert.as_ref(),
+        client_key_path: options.ssl_client_key.as_ref(),
+    }
+}
+
+// The TCP socket underneath a TLS stream that only lives for the LOGIN7 packet.
+//
+// Once the TLS stream is dropped this is the only reference left, and the socket can be
+// taken back to carry the rest of the session in plain text.
+#[derive(Clone)]
+pub(super) struct SharedSocket(Arc<Mutex<Box<dyn Socket>>>);
+
+impl SharedSocket {
+    pub(super) fn new(socket: Box<dyn Socket>) -> Self {
+        Self(Arc::new(Mutex::new(socket)))
+    }
+
+    pub(super) fn into_inner(self) -> Result<Box<dyn Socket>, Error> {
+        let socket = Arc::try_unwrap(self.0)
+            .map_err(|_| err_protocol!("socket is still in use by the TLS stream"))?;
+
+        Ok(socket.into_inner().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Box<dyn Socket>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Socket for SharedSocket {
+    fn try_read(&mut self, buf: &mut dyn ReadBuf) -> io::Result<usize> {
+        self.lock().try_read(buf)
+    }
+
+    fn try_write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().try_write(buf)
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.lock().poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.lock().poll_write_ready(cx)
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.lock().poll_flush(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.lock().poll_shutdown(cx)
+    }
+}
+
+// Wraps the socket underneath the TLS stream. While the handshake is in progress every
+// write is framed in a PRELOGIN packet and the packet headers are stripped from reads.
+// Afterwards it is a transparent pass-through.
+struct TlsPreloginWrapper<S> {
+    socket: S,
+    handshake_complete: Arc<AtomicBool>,
+
+    // header of the packet currently being read and how much of it has been read
+    header: [u8; PacketHeader::SIZE],
+    header_read: usize,
+
+    // remaining payload of the packet currently being read
+    payload_remaining: usize,
+
+    // framed packets that have not yet been written out to the socket
+    wbuf: Vec<u8>,
+    packet_id: u8,
+}
+
+impl<S: Socket> TlsPreloginWrapper<S> {
+    fn new(socket: S, handshake_complete: Arc<AtomicBool>) -> Self {
+        Self {
+            socket,
+            handshake_complete,
+            header: [0; PacketHeader::SIZE],
+            header_read: 0,
+            payload_remaining: 0,
+            wbuf: Vec::new(),
+            packet_id: 1,
+        }
+    }
+
+    fn in_handshake(&self) -> bool {
+        !self.handshake_complete.load(Ordering::Acquire)
+    }
+
+    // writes as much of the pending packets as the socket will take
+    fn try_write_pending(&mut self) -> io::Result<()> {
+        while !self.wbuf.is_empty() {
+            let written = self.socket.try_write(&self.wbuf)?;
+
+            if written == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+
+            self.wbuf.drain(..written);
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: Socket> Socket for TlsPreloginWrapper<S> {
+    fn try_read(&mut self, buf: &mut dyn ReadBuf) -> io::Result<usize> {
+        if !self.in_handshake() {
+            return self.socket.try_read(buf);
+        }
+
+        // the handshake is a conversation; anything of ours must be out before we
+        // can expect the server to answer
+        self.try_write_pending()?;
+
+        while self.payload_remaining == 0 {
+            let read = self
+                .socket
+                .try_read(&mut &mut self.header[self.header_read..])?;
+
+            if read == 0 {
+                return Ok(0);
+            }
+
+            self.header_read += read;
+
+            if self.header_read == PacketHeader::SIZE {
+                let length = u16::from_be_bytes([self.header[2], self.header[3]]) as usize;
+
+                self.header_read = 0;
+                self.payload_remaining = length.saturating_sub(PacketHeader::SIZE);
+            }
+        }
+
+        let dst = buf.init_mut();
+        let len = dst.len().min(self.payload_remaining);
+        let read = self.socket.try_read(&mut &mut dst[..len])?;
+
+        self.payload_remaining -= read;
+
+        Ok(read)
+    }
+
+    fn try_write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.in_handshake() {
+            return self.socket.try_write(buf);
+        }
+
+        // push back on the TLS stream until the previous flight is out
+        self.try_write_pending()?;
+
+        let mut chunks = buf
+            .chunks(PRELOGIN_PACKET_SIZE - PacketHeader::SIZE)
+            .peekable();
+
+        while let Some(chunk) = chunks.next() {
+            let status = if chunks.peek().is_none() {
+                Status::END_OF_MESSAGE
+            } else {
+                Status::NORMAL
+            };
+
+            PacketHeader {
+                r#type: PacketType::PreLogin,
+                status,
+                length: (chunk.len() + PacketHeader::SIZE) as u16,
+                server_process_id: 0,
+                packet_id: self.packet_id,
+            }
+            .encode_with(&mut self.wbuf, &mut 0);
+
+            self.wbuf.extend_from_slice(chunk);
+            self.packet_id = self.packet_id.wrapping_add(1);
+        }
+
+        // the data is ours now; whatever the socket does not take immediately is
+        // written on the next call
+        match self.try_write_pending() {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            res => res?,
+        }
+
+        Ok(buf.len())
+    }
+
+    fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if !self.wbuf.is_empty() {
+            // a read first needs to get our pending packets out
+            ready!(self.socket.poll_write_ready(cx))?;
+        }
+
+        self.socket.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.socket.poll_write_ready(cx)
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            match self.try_write_pending() {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    ready!(self.socket.poll_write_ready(cx))?;
+                }
+
+                Err(e) => return Poll::Ready(Err(e)),
+                Ok(()) => return self.socket.poll_flush(cx),
+            }
+        }
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.socket.poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+struct MockSocket {
+    rbuf: bytes::Bytes,
+    wbuf: Arc<std::sync::Mutex<Vec<u8>>>,
+}
+
+#[cfg(test)]
+impl Socket for MockSocket {
+    fn try_read(&mut self, buf: &mut dyn ReadBuf) -> io::Result<usize> {
+        let dst = buf.init_mut();
+        let len = dst.len().min(self.rbuf.len());
+        dst[..len].copy_from_slice(&self.rbuf.split_to(len));
+
+        Ok(len)
+    }
+
+    fn try_write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.wbuf.lock().unwrap().extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn poll_read_ready(&mut self, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_write_ready(&mut self, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(&mut self, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn test_prelogin_wrapper() {
+    let handshake_complete = Arc::new(AtomicBool::new(false));
+    let written = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    // two PRELOGIN packets from the server carrying 3 bytes of TLS data
+    #[rustfmt::skip]
+    let socket = MockSocket {
+        rbuf: bytes::Bytes::from_static(&[
+            0x04, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x01, 0x00, 0x16, 0x03,
+            0x04, 0x01, 0x00, 0x09, 0x00, 0x00, 0x02, 0x00, 0x01,
+            0x17,
+        ]),
+        wbuf: Arc::clone(&written),
+    };
+
+    let mut wrapper = TlsPreloginWrapper::new(socket, Arc::clone(&handshake_complete));
+
+    assert_eq!(wrapper.try_write(&[0x16, 0x03, 0x01]).unwrap(), 3);
+
+    let mut buf = [0_u8; 16];
+    let mut read = 0;
+
+    while read < 3 {
+        read += wrapper.try_read(&mut &mut buf[read..]).unwrap();
+    }
+
+    assert_eq!(&buf[..3], &[0x16, 0x03, 0x01]);
+
+    // after the handshake, bytes pass through untouched
+    handshake_complete.store(true, Ordering::Release);
+
+    assert_eq!(wrapper.try_write(&[0x17]).unwrap(), 1);
+    assert_eq!(wrapper.try_read(&mut &mut buf[..]).unwrap(), 1);
+    assert_eq!(buf[0], 0x17);
+
+    #[rustfmt::skip]
+    let expected = vec![
+        0x12, 0x01, 0x00, 0x0b, 0x00, 0x00, 0x01, 0x00, 0x16, 0x03, 0x01,
+        0x17,
+    ];
+
+    assert_eq!(expected, *written.lock().unwrap());
+}
+
+#[test]
+fn test_maybe_upgrade() {
+    use crate::connection::block_on;
+
+    let written = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let socket = || MockSocket {
+        rbuf: bytes::Bytes::new(),
+        wbuf: Arc::clone(&written),
+    };
+
+    // PRELOGIN decides about TLS unless the mode is strict
+    let options = MssqlConnectOptions::new().ssl_mode(MssqlSslMode::Require);
+    assert!(block_on(maybe_upgrade(socket(), &options)).is_ok());
+    assert!(written.lock().unwrap().is_empty());
+
+    let options = MssqlConnectOptions::new()
+        .host("db.example.com")
+        .ssl_mode(MssqlSslMode::Strict);
+
+    // the server hangs up on us, but not before the ClientHello went out
+    assert!(block_on(maybe_upgrade(socket(), &options)).is_err());
+
+    if tls::available() {
+        let written = written.lock().unwrap();
+
+        // a bare TLS handshake record, not wrapped in a PRELOGIN packet, offering `tds/8.0`
+        assert_eq!(written[0], 0x16);
+        assert!(written.windows(7).any(|alpn| alpn == b"tds/8.0"));
+    }
+}
+
+#[test]
+fn test_negotiate_encryption() {
+    use MssqlSslMode::*;
+
+    assert_eq!(
+        Allow.negotiate(Encrypt::OFF, true).unwrap(),
+        MssqlEncryption::LoginOnly
+    );
+    assert_eq!(
+        Allow.negotiate(Encrypt::REQUIRED, true).unwrap(),
+        MssqlEncryption::On
+    );
+    assert_eq!(
+        Prefer.negotiate(Encrypt::ON, true).unwrap(),
+        MssqlEncryption::On
+    );
+    assert_eq!(
+        Prefer.negotiate(Encrypt::NOT_SUPPORTED, true).unwrap(),
+        MssqlEncryption::Off
+    );
+    assert_eq!(
+        Disable.negotiate(Encrypt::NOT_SUPPORTED, true).unwrap(),
+        MssqlEncryption::Off
+    );
+
+    assert_eq!(
+        Strict.negotiate(Encrypt::NOT_SUPPORTED, true).unwrap(),
+        MssqlEncryption::Strict
+    );
+
+    assert!(Disable.negotiate(Encrypt::REQUIRED, true).is_err());
+    assert!(Require.negotiate(Encrypt::OFF, true).is_err());
+    assert!(VerifyFull.negotiate(Encrypt::NOT_SUPPORTED, true).is_err());
+
+    // without a TLS backend
+    assert_eq!(Prefer.requested_encryption(false), Encrypt::NOT_SUPPORTED);
+    assert_eq!(
+        Prefer.negotiate(Encrypt::NOT_SUPPORTED, false).unwrap(),
+        MssqlEncryption::Off
+    );
+
+    assert_eq!(Allow.requested_encryption(false), Encrypt::NOT_SUPPORTED);
+    assert_eq!(
+        Allow.negotiate(Encrypt::OFF, false).unwrap(),
+        MssqlEncryption::Off
+    );
+    assert_eq!(
+        Prefer.negotiate(Encrypt::OFF, false).unwrap(),
+        MssqlEncryption::Off
+    );
+
+    assert!(Prefer.negotiate(Encrypt::REQUIRED, false).is_err());
+    assert!(Require.negotiate(Encrypt::OFF, false).is_err());
+    assert!(Require.negotiate(Encrypt::NOT_SUPPORTED, false).is_err());
 }
@@ -25,7 +25,7 @@ impl Database for Mssql {
 
     const NAME: &'static str = "Mssql";
 
-    const URL_SCHEMES: &'static [&'static str] = &["mssql"];
+    const URL_SCHEMES: &'static [&'static str] = &["mssql", "sqlserver"];
 }
 
 impl<'r> HasValueRef<'r> for Mssql {
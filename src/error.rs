@@ -1,12 +1,45 @@
 use crate::protocol::error::Error as ProtoError;
+use std::borrow::Cow;
 use std::error::Error as StdError;
 use std::fmt::{self, Debug, Display, Formatter};
 
 pub(crate) use sqlx_core::error::*;
 
 /// An error returned from the MSSQL database.
+///
+/// SQL Server raises the same error number for conflicts with FOREIGN KEY and CHECK
+/// constraints. [`kind`](DatabaseError::kind) tells them apart by the English message
+/// of the server, and reports `Other` for a message in another language.
 pub struct MssqlDatabaseError(pub(crate) ProtoError);
 
+impl MssqlDatabaseError {
+    /// The error number, as found in `sys.messages`.
+    pub fn number(&self) -> i32 {
+        self.0.number
+    }
+
+    /// The error state, used as a modifier to the error number.
+    pub fn state(&self) -> u8 {
+        self.0.state
+    }
+
+    /// The class (severity) of the error.
+    pub fn class(&self) -> u8 {
+        self.0.class
+    }
+
+    /// The name of the stored procedure that raised the error, if any.
+    pub fn procedure(&self) -> &str {
+        &self.0.procedure
+    }
+
+    /// The line number in the SQL batch or stored procedure that caused the error,
+    /// starting at 1; or 0 if not applicable.
+    pub fn line(&self) -> i32 {
+        self.0.line
+    }
+}
+
 impl Debug for MssqlDatabaseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("MssqlDatabaseError")
@@ -35,6 +68,10 @@ impl DatabaseError for MssqlDatabaseError {
         &self.0.message
     }
 
+    fn code(&self) -> Option<Cow<'_, str>> {
+        Some(self.0.number.to_string().into())
+    }
+
     #[doc(hidden)]
     fn as_error(&self) -> &(dyn StdError + Send + Sync + 'static) {
         self
@@ -51,13 +88,115 @@ impl DatabaseError for MssqlDatabaseError {
     }
 
     fn kind(&self) -> ErrorKind {
-        //    //error_codes::UNIQUE_VIOLATION => ErrorKind::UniqueViolation,
-        //    //error_codes::FOREIGN_KEY_VIOLATION => ErrorKind::ForeignKeyViolation,
-        //    //error_codes::NOT_NULL_VIOLATION => ErrorKind::NotNullViolation,
-        //    //error_codes::CHECK_VIOLATION => ErrorKind::CheckViolation,
-        //    //_ => ErrorKind::Other,
-        //}
-        //TODO: // report the correct KIND of error
-        ErrorKind::Other
+        match self.0.number {
+            error_codes::UNIQUE_CONSTRAINT_VIOLATION | error_codes::UNIQUE_INDEX_VIOLATION => {
+                ErrorKind::UniqueViolation
+            }
+
+            // the same number is raised for both; only the message tells them apart
+            error_codes::CONSTRAINT_CONFLICT => match conflicting_constraint(&self.0.message) {
+                Some("CHECK") => ErrorKind::CheckViolation,
+                Some("FOREIGN KEY" | "REFERENCE") => ErrorKind::ForeignKeyViolation,
+                _ => ErrorKind::Other,
+            },
+
+            error_codes::NOT_NULL_VIOLATION => ErrorKind::NotNullViolation,
+
+            _ => ErrorKind::Other,
+        }
     }
 }
+
+// the kind of constraint named by the message of error 547, as in
+// `The INSERT statement conflicted with the CHECK constraint "CK_price". ...`
+fn conflicting_constraint(message: &str) -> Option<&str> {
+    let (_, rest) = message.split_once(" conflicted with the ")?;
+    let (kind, _) = rest.split_once(" constraint \"")?;
+
+    Some(kind)
+}
+
+/// The error numbers that map to an [`ErrorKind`].
+///
+/// For the complete list see `SELECT * FROM sys.messages WHERE language_id = 1033`.
+pub(crate) mod error_codes {
+    /// Violation of PRIMARY KEY or UNIQUE constraint.
+    pub const UNIQUE_CONSTRAINT_VIOLATION: i32 = 2627;
+
+    /// Cannot insert duplicate key row in object with unique index.
+    pub const UNIQUE_INDEX_VIOLATION: i32 = 2601;
+
+    /// The statement conflicted with a FOREIGN KEY, REFERENCE or CHECK constraint.
+    pub const CONSTRAINT_CONFLICT: i32 = 547;
+
+    /// Cannot insert the value NULL into a column that does not allow nulls.
+    pub const NOT_NULL_VIOLATION: i32 = 515;
+}
+
+#[test]
+fn test_error_kind() {
+    fn error(number: i32, message: &str) -> MssqlDatabaseError {
+        MssqlDatabaseError(ProtoError {
+            number,
+            state: 1,
+            class: 14,
+            message: message.into(),
+            server: String::new(),
+            procedure: String::new(),
+            line: 1,
+        })
+    }
+
+    assert!(matches!(
+        error(2627, "Violation of PRIMARY KEY constraint 'PK_users'.").kind(),
+        ErrorKind::UniqueViolation
+    ));
+
+    assert!(matches!(
+        error(
+            547,
+            "The INSERT statement conflicted with the FOREIGN KEY constraint \"FK_orders_users\"."
+        )
+        .kind(),
+        ErrorKind::ForeignKeyViolation
+    ));
+
+    assert!(matches!(
+        error(
+            547,
+            "The INSERT statement conflicted with the CHECK constraint \"CK_price\"."
+        )
+        .kind(),
+        ErrorKind::CheckViolation
+    ));
+
+    // a FOREIGN KEY constraint with an unfortunate name
+    assert!(matches!(
+        error(
+            547,
+            "The DELETE statement conflicted with the REFERENCE constraint \"CHECK constraint\"."
+        )
+        .kind(),
+        ErrorKind::ForeignKeyViolation
+    ));
+
+    // the message of a server set to German
+    assert!(matches!(
+        error(
+            547,
+            "Die INSERT-Anweisung steht in Konflikt mit der CHECK-Einschränkung \"CK_price\"."
+        )
+        .kind(),
+        ErrorKind::Other
+    ));
+
+    assert!(matches!(
+        error(515, "Cannot insert the value NULL into column 'name'.").kind(),
+        ErrorKind::NotNullViolation
+    ));
+
+    assert!(matches!(
+        error(208, "Invalid object name 'foo'.").kind(),
+        ErrorKind::Other
+    ));
+}
@@ -0,0 +1,46 @@
+use crate::protocol::info::Info;
+
+/// An informational message sent by the server, such as the output of `PRINT` or of a
+/// `RAISERROR` with a severity of 10 or lower.
+///
+/// See [`MssqlConnection::set_info_handler`][crate::MssqlConnection::set_info_handler].
+#[derive(Debug, Clone)]
+pub struct MssqlInfo(pub(crate) Info);
+
+impl MssqlInfo {
+    /// The message number, as found in `sys.messages`; or 0 for `PRINT`.
+    pub fn number(&self) -> i32 {
+        self.0.number
+    }
+
+    /// The message state, used as a modifier to the message number.
+    pub fn state(&self) -> u8 {
+        self.0.state
+    }
+
+    /// The class (severity) of the message.
+    pub fn class(&self) -> u8 {
+        self.0.class
+    }
+
+    /// The message text.
+    pub fn message(&self) -> &str {
+        &self.0.message
+    }
+
+    /// The name of the server that sent the message.
+    pub fn server(&self) -> &str {
+        &self.0.server
+    }
+
+    /// The name of the stored procedure that sent the message, if any.
+    pub fn procedure(&self) -> &str {
+        &self.0.procedure
+    }
+
+    /// The line number in the SQL batch or stored procedure that sent the message,
+    /// starting at 1; or 0 if not applicable.
+    pub fn line(&self) -> i32 {
+        self.0.line
+    }
+}
@@ -9,13 +9,16 @@ use crate::executor::Executor;
 pub mod any;
 
 mod arguments;
+mod bulk;
 mod column;
 mod connection;
 mod database;
 mod error;
 //mod ext;
+mod info;
 mod io;
 mod options;
+mod procedure;
 mod protocol;
 mod query_result;
 mod row;
@@ -26,11 +29,14 @@ pub mod types;
 mod value;
 
 pub use arguments::MssqlArguments;
+pub use bulk::MssqlBulkInsert;
 pub use column::MssqlColumn;
-pub use connection::MssqlConnection;
+pub use connection::{MssqlConnection, MssqlEncryption};
 pub use database::Mssql;
 pub use error::MssqlDatabaseError;
-pub use options::{MssqlConnectOptions, MssqlSslMode};
+pub use info::MssqlInfo;
+pub use options::{MssqlApplicationIntent, MssqlConnectOptions, MssqlSslMode};
+pub use procedure::MssqlProcedureCall;
 pub use query_result::MssqlQueryResult;
 pub use row::MssqlRow;
 pub use statement::MssqlStatement;
@@ -0,0 +1,333 @@
+use super::keyword::{parse_value, Keyword, Settings};
+use crate::error::Error;
+use crate::MssqlConnectOptions;
+
+impl MssqlConnectOptions {
+    /// Parses a connection string in the `keyword=value;...` format used by ADO.NET and
+    /// most other SQL Server clients.
+    ///
+    /// Keywords are case-insensitive and may be written with or without their spaces, and
+    /// the usual synonyms are accepted (`Data Source` for `Server`, `Initial Catalog` for
+    /// `Database`, `UID` for `User ID`, and so on). Values may be quoted with `"`, `'` or
+    /// `{}`, doubling the closing character to include it in the value. Keywords that only
+    /// configure the client, such as `Pooling`, `Max Pool Size` or an ODBC `Driver`, are
+    /// ignored; any other unknown keyword is an error.
+    ///
+    /// `Encrypt=True` verifies the server certificate unless `TrustServerCertificate=True`
+    /// is given as well; `Encrypt=Strict` connects with TDS 8.0 strict encryption.
+    ///
+    /// Parsing a `MssqlConnectOptions` from a string falls back to this for anything that
+    /// is not a URL.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::MssqlConnectOptions;
+    /// let options = MssqlConnectOptions::from_ado_string(
+    ///     "Server=tcp:db.example.com,1433;Database=app;User Id=app;Password={p;ss};Encrypt=True",
+    /// )?;
+    /// # Ok::<(), sqlx_core::Error>(())
+    /// ```
+    pub fn from_ado_string(s: &str) -> Result<Self, Error> {
+        let mut settings = Settings::new();
+
+        for (key, value) in pairs(s)? {
+            if let "server" | "datasource" | "address" | "addr" | "networkaddress" = &*key {
+                settings.options = server(settings.options, &value)?;
+                continue;
+            }
+
+            if IGNORED_KEYWORDS.contains(&&*key) {
+                log::debug!("ignoring connection string keyword {:?}", key);
+                continue;
+            }
+
+            let keyword = keyword(&key).ok_or_else(|| {
+                Error::Configuration(format!("unknown connection string keyword {:?}", key).into())
+            })?;
+
+            settings = settings.set(keyword, &key, &value)?;
+        }
+
+        settings.finish()
+    }
+}
+
+// keywords, lowercased and without whitespace, that only configure the ADO.NET or ODBC
+// client itself (its connection pool, retries, command timeout, driver) and have nothing
+// to do here
+const IGNORED_KEYWORDS: [&str; 15] = [
+    "pooling",
+    "maxpoolsize",
+    "minpoolsize",
+    "connectionlifetime",
+    "loadbalancetimeout",
+    "connectionreset",
+    "poolblockingperiod",
+    "enlist",
+    "multisubnetfailover",
+    "transparentnetworkipresolution",
+    "connectretrycount",
+    "connectretryinterval",
+    "commandtimeout",
+    "driver",
+    "provider",
+];
+
+// the keyword of a connection string, lowercased and without whitespace
+fn keyword(key: &str) -> Option<Keyword> {
+    Some(match key {
+        "database" | "initialcatalog" => Keyword::Database,
+        "userid" | "uid" | "user" => Keyword::Username,
+        "password" | "pwd" => Keyword::Password,
+        "encrypt" => Keyword::Encrypt,
+        "trustservercertificate" => Keyword::TrustServerCertificate,
+        "hostnameincertificate" => Keyword::SslHostName,
+        "applicationname" | "app" => Keyword::AppName,
+        "workstationid" | "wsid" => Keyword::WorkstationName,
+        "currentlanguage" | "language" => Keyword::Language,
+        "packetsize" => Keyword::PacketSize,
+        "connecttimeout" | "connectiontimeout" | "timeout" => Keyword::ConnectTimeout,
+        "applicationintent" => Keyword::ApplicationIntent,
+        "persistsecurityinfo" => Keyword::PersistSecurityInfo,
+        "integratedsecurity" | "trustedconnection" => Keyword::IntegratedSecurity,
+        "multipleactiveresultsets" => Keyword::MultipleActiveResultSets,
+        "authentication" => Keyword::Authentication,
+        _ => return None,
+    })
+}
+
+// applies a `[tcp:]host[\instance][,port]` server value, where an IPv6 host may be in
+// brackets
+fn server(mut options: MssqlConnectOptions, value: &str) -> Result<MssqlConnectOptions, Error> {
+    let mut value = value;
+
+    // the protocol prefix is one of a few words, which sets it apart from an IPv6 address
+    if let Some((protocol, rest)) = value.split_once(':') {
+        if protocol.eq_ignore_ascii_case("tcp") {
+            value = rest;
+        } else if ["np", "lpc", "admin"]
+            .iter()
+            .any(|p| protocol.eq_ignore_ascii_case(p))
+        {
+            return Err(Error::Configuration(
+                format!(
+                    "unsupported protocol {:?} for `server`, only tcp is",
+                    protocol
+                )
+                .into(),
+            ));
+        }
+    }
+
+    if let Some((rest, port)) = value.rsplit_once(',') {
+        options = options.port(parse_value("server", port.trim())?);
+        value = rest;
+    }
+
+    if let Some((rest, instance)) = value.split_once('\\') {
+        options = options.instance_name(instance);
+        value = rest;
+    }
+
+    let value = match value.strip_prefix('[') {
+        Some(host) => host.strip_suffix(']').ok_or_else(|| {
+            Error::Configuration(format!("unterminated IPv6 address in {:?}", value).into())
+        })?,
+
+        None => value,
+    };
+
+    Ok(match value {
+        "." | "(local)" => options.host("localhost"),
+        host => options.host(host),
+    })
+}
+
+// splits a connection string into its keywords, lowercased and without whitespace, and
+// their unquoted values
+pub(super) fn pairs(s: &str) -> Result<Vec<(String, String)>, Error> {
+    let mut pairs = Vec::new();
+    let mut rest = s;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ';' || c.is_whitespace());
+
+        if rest.is_empty() {
+            return Ok(pairs);
+        }
+
+        // the keyword runs up to the first `=` that is not doubled
+        let mut key = String::new();
+        let mut chars = rest.char_indices();
+
+        let value_start = loop {
+            match chars.next() {
+                Some((i, '=')) if rest[i + 1..].starts_with('=') => {
+                    key.push('=');
+                    chars.next();
+                }
+
+                Some((i, '=')) => break i + 1,
+
+                Some((_, ';')) | None => {
+                    return Err(Error::Configuration(
+                        format!("missing value for connection string keyword {:?}", key).into(),
+                    ));
+                }
+
+                Some((_, c)) => key.push(c),
+            }
+        };
+
+        let key: String = key
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect();
+
+        if key.is_empty() {
+            return Err(Error::Configuration(
+                "missing keyword in connection string".into(),
+            ));
+        }
+
+        rest = rest[value_start..].trim_start();
+
+        let value = match rest.chars().next() {
+            Some(quote @ ('"' | '\'' | '{')) => {
+                let close = if quote == '{' { '}' } else { quote };
+
+                let mut value = String::new();
+                let mut chars = rest.char_indices().skip(1);
+
+                // the value runs up to the first closing character that is not doubled
+                let value_end = loop {
+                    match chars.next() {
+                        Some((i, c)) if c == close && rest[i + 1..].starts_with(close) => {
+                            value.push(close);
+                            chars.next();
+                        }
+
+                        Some((i, c)) if c == close => break i + 1,
+
+                        Some((_, c)) => value.push(c),
+
+                        None => {
+                            return Err(Error::Configuration(
+                                format!(
+                                    "unterminated value for connection string keyword {:?}",
+                                    key
+                                )
+                                .into(),
+                            ));
+                        }
+                    }
+                };
+
+                rest = rest[value_end..].trim_start();
+
+                if !rest.is_empty() && !rest.starts_with(';') {
+                    return Err(Error::Configuration(
+                        format!(
+                            "unexpected text after the value of connection string keyword {:?}",
+                            key
+                        )
+                        .into(),
+                    ));
+                }
+
+                value
+            }
+
+            _ => {
+                let value_end = rest.find(';').unwrap_or(rest.len());
+                let value = rest[..value_end].trim_end().to_owned();
+
+                rest = &rest[value_end..];
+
+                value
+            }
+        };
+
+        pairs.push((key, value));
+    }
+}
+
+#[test]
+fn it_splits_connection_string_pairs() {
+    let split =
+        pairs(" Data Source = host ;;PWD={a;b}}c};App='it''s';Key==Name=\"x\" ; Empty=").unwrap();
+
+    assert_eq!(
+        split,
+        [
+            ("datasource".into(), "host".into()),
+            ("pwd".into(), "a;b}c".into()),
+            ("app".into(), "it's".into()),
+            ("key=name".into(), "x".into()),
+            ("empty".into(), String::new()),
+        ]
+    );
+
+    assert!(pairs("Password={abc").is_err());
+    assert!(pairs("Password={abc}d").is_err());
+    assert!(pairs("Server").is_err());
+}
+
+#[test]
+fn it_parses_ado_strings() {
+    use crate::MssqlSslMode;
+    use std::time::Duration;
+
+    let opts = MssqlConnectOptions::from_ado_string(
+        "Server=tcp:db.example.com,14330;Initial Catalog=app;Persist Security Info=False;\
+         User ID=app;Password={p;ss};MultipleActiveResultSets=False;Encrypt=True;\
+         TrustServerCertificate=False;Connection Timeout=30;",
+    )
+    .unwrap();
+
+    assert_eq!("db.example.com", opts.host);
+    assert_eq!(14330, opts.port);
+    assert_eq!("app", opts.database);
+    assert_eq!("app", opts.username);
+    assert_eq!(Some("p;ss"), opts.password.as_deref());
+    assert!(matches!(opts.ssl_mode, MssqlSslMode::VerifyFull));
+    assert_eq!(Some(Duration::from_secs(30)), opts.connect_timeout);
+
+    let opts = MssqlConnectOptions::from_ado_string(
+        "Data Source=.\\SQLEXPRESS;UID=sa;Encrypt=yes;TrustServerCertificate=yes",
+    )
+    .unwrap();
+
+    assert_eq!("localhost", opts.host);
+    assert_eq!(Some("SQLEXPRESS"), opts.instance_name.as_deref());
+    assert!(matches!(opts.ssl_mode, MssqlSslMode::Require));
+
+    for (server, host, port) in [
+        ("::1,1500", "::1", 1500),
+        ("tcp:[::1],1500", "::1", 1500),
+        ("[fe80::1]", "fe80::1", 1433),
+        ("cafe::1", "cafe::1", 1433),
+    ] {
+        let opts = MssqlConnectOptions::from_ado_string(&format!("Server={}", server)).unwrap();
+        assert_eq!(host, opts.host);
+        assert_eq!(port, opts.port);
+    }
+
+    // a pooled connection string, with keywords for the client only
+    let opts = MssqlConnectOptions::from_ado_string(
+        "Driver={ODBC Driver 18 for SQL Server};Server=db.example.com;Database=app;\
+         Pooling=True;Max Pool Size=100;Min Pool Size=5;MultiSubnetFailover=True;\
+         Connect Retry Count=3;Connect Retry Interval=10;Command Timeout=60",
+    )
+    .unwrap();
+
+    assert_eq!("db.example.com", opts.host);
+    assert_eq!("app", opts.database);
+
+    assert!(MssqlConnectOptions::from_ado_string("Server=np:host").is_err());
+    assert!(MssqlConnectOptions::from_ado_string("Server=[::1").is_err());
+    assert!(MssqlConnectOptions::from_ado_string("Server=host;Colour=blue").is_err());
+    assert!(MssqlConnectOptions::from_ado_string("Integrated Security=SSPI").is_err());
+}
@@ -0,0 +1,34 @@
+use crate::error::Error;
+use std::str::FromStr;
+
+/// The kind of workload the application declares when logging in to the server.
+///
+/// It is used by the [`application_intent`](super::MssqlConnectOptions::application_intent)
+/// method.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MssqlApplicationIntent {
+    /// The application reads and writes data.
+    #[default]
+    ReadWrite,
+
+    /// The application only reads data. An availability group listener with read-only
+    /// routing sends such connections to a readable secondary replica.
+    ReadOnly,
+}
+
+impl FromStr for MssqlApplicationIntent {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Ok(match &*s.to_ascii_lowercase() {
+            "readwrite" | "read-write" | "read_write" => MssqlApplicationIntent::ReadWrite,
+            "readonly" | "read-only" | "read_only" => MssqlApplicationIntent::ReadOnly,
+
+            _ => {
+                return Err(Error::Configuration(
+                    format!("unknown value {:?} for `application_intent`", s).into(),
+                ));
+            }
+        })
+    }
+}
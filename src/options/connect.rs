@@ -1,9 +1,11 @@
+use super::keyword::{Keyword, Settings};
 use crate::error::Error;
 use crate::{MssqlConnectOptions, MssqlConnection};
 use futures_core::future::BoxFuture;
 use log::LevelFilter;
 use percent_encoding::percent_decode_str;
 use sqlx_core::connection::ConnectOptions;
+use std::io;
 use std::time::Duration;
 use url::Url;
 
@@ -11,10 +13,24 @@ impl ConnectOptions for MssqlConnectOptions {
     type Connection = MssqlConnection;
 
     fn from_url(url: &Url) -> Result<Self, Error> {
-        let mut options = Self::new();
+        let mut settings = Settings::new();
+        let mut options = settings.options;
 
         if let Some(host) = url.host_str() {
-            options = options.host(host);
+            let host = percent_decode_str(host)
+                .decode_utf8()
+                .map_err(Error::config)?;
+
+            // `host\instance` for a named instance
+            match host.split_once('\\') {
+                Some((host, instance)) => {
+                    options = options.host(host).instance_name(instance);
+                }
+
+                None => {
+                    options = options.host(&host);
+                }
+            }
         }
 
         if let Some(port) = url.port() {
@@ -24,7 +40,7 @@ impl ConnectOptions for MssqlConnectOptions {
         let username = url.username();
         if !username.is_empty() {
             options = options.username(
-                &*percent_decode_str(username)
+                &percent_decode_str(username)
                     .decode_utf8()
                     .map_err(Error::config)?,
             );
@@ -32,7 +48,7 @@ impl ConnectOptions for MssqlConnectOptions {
 
         if let Some(password) = url.password() {
             options = options.password(
-                &*percent_decode_str(password)
+                &percent_decode_str(password)
                     .decode_utf8()
                     .map_err(Error::config)?,
             );
@@ -43,14 +59,37 @@ impl ConnectOptions for MssqlConnectOptions {
             options = options.database(path);
         }
 
-        Ok(options)
+        settings.options = options;
+
+        for (key, value) in url.query_pairs() {
+            let keyword = keyword(&key).ok_or_else(|| {
+                Error::Configuration(format!("unknown connection URL parameter {:?}", key).into())
+            })?;
+
+            settings = settings.set(keyword, &key, &value)?;
+        }
+
+        settings.finish()
     }
 
     fn connect(&self) -> BoxFuture<'_, Result<Self::Connection, Error>>
     where
         Self::Connection: Sized,
     {
-        Box::pin(MssqlConnection::establish(self))
+        Box::pin(async move {
+            let Some(timeout) = self.connect_timeout else {
+                return MssqlConnection::establish(self).await;
+            };
+
+            sqlx_core::rt::timeout(timeout, MssqlConnection::establish(self))
+                .await
+                .map_err(|_| {
+                    Error::Io(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "timed out connecting to the server",
+                    ))
+                })?
+        })
     }
 
     fn log_statements(mut self, level: LevelFilter) -> Self {
@@ -63,3 +102,27 @@ impl ConnectOptions for MssqlConnectOptions {
         self
     }
 }
+
+// the keyword of a URL query parameter, in snake case or kebab case
+fn keyword(key: &str) -> Option<Keyword> {
+    Some(match &*key.to_ascii_lowercase().replace('-', "_") {
+        "database" => Keyword::Database,
+        "instance" | "instance_name" => Keyword::InstanceName,
+        "sslmode" | "ssl_mode" => Keyword::SslMode,
+        "sslrootcert" | "ssl_root_cert" | "sslca" | "ssl_ca" => Keyword::SslRootCert,
+        "sslcert" | "ssl_cert" => Keyword::SslClientCert,
+        "sslkey" | "ssl_key" => Keyword::SslClientKey,
+        "ssl_host_name" | "host_name_in_certificate" => Keyword::SslHostName,
+        "encrypt" => Keyword::Encrypt,
+        "trust_server_certificate" | "trustservercertificate" => Keyword::TrustServerCertificate,
+        "app_name" | "application_name" => Keyword::AppName,
+        "workstation_name" | "workstation_id" => Keyword::WorkstationName,
+        "language" => Keyword::Language,
+        "packet_size" => Keyword::PacketSize,
+        "connect_timeout" | "connection_timeout" => Keyword::ConnectTimeout,
+        "application_intent" => Keyword::ApplicationIntent,
+        "statement_cache_capacity" => Keyword::StatementCacheCapacity,
+        "integrated_security" | "trusted_connection" => Keyword::IntegratedSecurity,
+        _ => return None,
+    })
+}
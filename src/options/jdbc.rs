@@ -0,0 +1,145 @@
+use super::ado::pairs;
+use super::keyword::{parse_value, Keyword, Settings};
+use crate::error::Error;
+use crate::MssqlConnectOptions;
+
+// parses the part after `sqlserver://` of a URL in the format of the Microsoft JDBC driver,
+// `[server[\instance][:port]][;property=value...]`
+pub(super) fn from_jdbc(s: &str) -> Result<MssqlConnectOptions, Error> {
+    let mut settings = Settings::new();
+
+    let (server_part, properties) = s.split_once(';').unwrap_or((s, ""));
+
+    if !server_part.is_empty() {
+        settings.options = server(settings.options, server_part)?;
+    }
+
+    for (key, value) in pairs(properties)? {
+        if let "servername" | "server" = &*key {
+            settings.options = server(settings.options, &value)?;
+            continue;
+        }
+
+        if IGNORED_PROPERTIES.contains(&&*key) {
+            log::debug!("ignoring JDBC property {:?}", key);
+            continue;
+        }
+
+        let keyword = keyword(&key).ok_or_else(|| {
+            Error::Configuration(format!("unknown connection URL property {:?}", key).into())
+        })?;
+
+        settings = settings.set(keyword, &key, &value)?;
+    }
+
+    settings.finish()
+}
+
+// properties, lowercased, that only change how the JDBC driver itself behaves and have
+// nothing to do here
+const IGNORED_PROPERTIES: [&str; 10] = [
+    "sendstringparametersasunicode",
+    "sendtimeasdatetime",
+    "selectmethod",
+    "responsebuffering",
+    "lastupdatecount",
+    "xopenstates",
+    "disablestatementpooling",
+    "statementpoolingcachesize",
+    "enableprepareonfirstpreparedstatementcall",
+    "serverpreparedstatementdiscardthreshold",
+];
+
+// the name of a property, lowercased
+fn keyword(key: &str) -> Option<Keyword> {
+    Some(match key {
+        "instancename" => Keyword::InstanceName,
+        "portnumber" | "port" => Keyword::Port,
+        "databasename" | "database" => Keyword::Database,
+        "user" | "username" => Keyword::Username,
+        "password" => Keyword::Password,
+        "encrypt" => Keyword::Encrypt,
+        "trustservercertificate" => Keyword::TrustServerCertificate,
+        "hostnameincertificate" => Keyword::SslHostName,
+        "applicationname" => Keyword::AppName,
+        "workstationid" => Keyword::WorkstationName,
+        "language" => Keyword::Language,
+        "packetsize" => Keyword::PacketSize,
+        "logintimeout" => Keyword::ConnectTimeout,
+        "applicationintent" => Keyword::ApplicationIntent,
+        "integratedsecurity" => Keyword::IntegratedSecurity,
+        "authentication" => Keyword::Authentication,
+        _ => return None,
+    })
+}
+
+// applies a `host[\instance][:port]` server value, where an IPv6 host is in brackets
+fn server(mut options: MssqlConnectOptions, value: &str) -> Result<MssqlConnectOptions, Error> {
+    let (host, mut rest) = match value.strip_prefix('[') {
+        Some(value) => value.split_once(']').ok_or_else(|| {
+            Error::Configuration(format!("unterminated IPv6 address in {:?}", value).into())
+        })?,
+
+        None => {
+            let end = value.find([':', '\\']).unwrap_or(value.len());
+            value.split_at(end)
+        }
+    };
+
+    if let Some((instance, port)) = rest.rsplit_once(':') {
+        options = options.port(parse_value("port", port)?);
+        rest = instance;
+    }
+
+    if let Some(instance) = rest.strip_prefix('\\') {
+        options = options.instance_name(instance);
+    } else if !rest.is_empty() {
+        return Err(Error::Configuration(
+            format!("invalid server {:?} in connection URL", value).into(),
+        ));
+    }
+
+    Ok(options.host(host))
+}
+
+#[test]
+fn it_parses_jdbc_urls() {
+    use crate::MssqlSslMode;
+    use std::time::Duration;
+
+    let opts = from_jdbc(
+        "db.example.com:14330;databaseName=app;user=app;password={p;ss};encrypt=true;\
+         trustServerCertificate=true;loginTimeout=30",
+    )
+    .unwrap();
+
+    assert_eq!("db.example.com", opts.host);
+    assert_eq!(14330, opts.port);
+    assert_eq!("app", opts.database);
+    assert_eq!("app", opts.username);
+    assert_eq!(Some("p;ss"), opts.password.as_deref());
+    assert!(matches!(opts.ssl_mode, MssqlSslMode::Require));
+    assert_eq!(Some(Duration::from_secs(30)), opts.connect_timeout);
+
+    let opts = from_jdbc(";serverName=host\\SQLEXPRESS;portNumber=1500").unwrap();
+    assert_eq!("host", opts.host);
+    assert_eq!(Some("SQLEXPRESS"), opts.instance_name.as_deref());
+    assert_eq!(1500, opts.port);
+
+    let opts = from_jdbc("[::1]:1500").unwrap();
+    assert_eq!("::1", opts.host);
+    assert_eq!(1500, opts.port);
+
+    let opts = from_jdbc(
+        "10.0.0.5;hostNameInCertificate=db.example.com;sendStringParametersAsUnicode=false;\
+         authentication=SqlPassword",
+    )
+    .unwrap();
+
+    assert_eq!("10.0.0.5", opts.host);
+    assert_eq!("db.example.com", opts.certificate_host());
+
+    assert!(from_jdbc("host;authentication=ActiveDirectoryPassword").is_err());
+    assert!(from_jdbc("host;colour=blue").is_err());
+    assert!(from_jdbc("host:port").is_err());
+}
@@ -0,0 +1,170 @@
+use crate::error::Error;
+use crate::MssqlConnectOptions;
+use std::str::FromStr;
+use std::time::Duration;
+
+// the settings that a URL, an ADO.NET connection string and a JDBC URL have in common; each
+// syntax maps its own keywords onto these, and they are applied the same way for all of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Keyword {
+    Database,
+    Username,
+    Password,
+    InstanceName,
+    Port,
+    SslMode,
+    SslRootCert,
+    SslClientCert,
+    SslClientKey,
+    SslHostName,
+    Encrypt,
+    TrustServerCertificate,
+    AppName,
+    WorkstationName,
+    Language,
+    PacketSize,
+    ConnectTimeout,
+    ApplicationIntent,
+    StatementCacheCapacity,
+    IntegratedSecurity,
+    MultipleActiveResultSets,
+    Authentication,
+    // only tells ADO.NET whether to keep the password around; we always do
+    PersistSecurityInfo,
+}
+
+// options being built up from the keywords of a connection string, in any order
+pub(super) struct Settings {
+    pub(super) options: MssqlConnectOptions,
+    // the encryption depends on both of these, which may come in either order
+    encrypt: Option<String>,
+    trust_server_certificate: Option<bool>,
+}
+
+impl Settings {
+    pub(super) fn new() -> Self {
+        Self {
+            options: MssqlConnectOptions::new(),
+            encrypt: None,
+            trust_server_certificate: None,
+        }
+    }
+
+    // applies the value given for a keyword, named [key] as written in the connection string
+    pub(super) fn set(mut self, keyword: Keyword, key: &str, value: &str) -> Result<Self, Error> {
+        let options = self.options;
+
+        self.options = match keyword {
+            Keyword::Database => options.database(value),
+            Keyword::Username => options.username(value),
+            Keyword::Password => options.password(value),
+            Keyword::InstanceName => options.instance_name(value),
+            Keyword::Port => options.port(parse_value(key, value)?),
+            Keyword::SslMode => options.ssl_mode(value.parse()?),
+            Keyword::SslRootCert => options.ssl_root_cert(value),
+            Keyword::SslClientCert => options.ssl_client_cert(value),
+            Keyword::SslClientKey => options.ssl_client_key(value),
+            Keyword::SslHostName => options.ssl_host_name(value),
+
+            Keyword::Encrypt => {
+                self.encrypt = Some(value.to_owned());
+                options
+            }
+
+            Keyword::TrustServerCertificate => {
+                self.trust_server_certificate = Some(parse_bool(key, value)?);
+                options
+            }
+
+            Keyword::AppName => options.app_name(value),
+            Keyword::WorkstationName => options.workstation_name(value),
+            Keyword::Language => options.language(value),
+            Keyword::PacketSize => options.packet_size(parse_value(key, value)?),
+
+            Keyword::ConnectTimeout => {
+                // in seconds, with 0 meaning no limit
+                let seconds = parse_value(key, value)?;
+
+                if seconds > 0 {
+                    options.connect_timeout(Duration::from_secs(seconds))
+                } else {
+                    options
+                }
+            }
+
+            Keyword::ApplicationIntent => options.application_intent(value.parse()?),
+            Keyword::StatementCacheCapacity => {
+                options.statement_cache_capacity(parse_value(key, value)?)
+            }
+
+            Keyword::IntegratedSecurity => {
+                if value.eq_ignore_ascii_case("sspi") || parse_bool(key, value)? {
+                    return Err(Error::Configuration(
+                        "integrated security is not supported".into(),
+                    ));
+                }
+
+                options
+            }
+
+            Keyword::MultipleActiveResultSets => {
+                if parse_bool(key, value)? {
+                    return Err(Error::Configuration(
+                        "multiple active result sets are not supported".into(),
+                    ));
+                }
+
+                options
+            }
+
+            Keyword::Authentication => {
+                // SQL Server authentication with the user and password is all there is
+                let method: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+
+                if !["sqlpassword", "notspecified"]
+                    .iter()
+                    .any(|m| method.eq_ignore_ascii_case(m))
+                {
+                    return Err(Error::Configuration(
+                        format!("unsupported authentication {:?}", value).into(),
+                    ));
+                }
+
+                options
+            }
+
+            Keyword::PersistSecurityInfo => {
+                parse_bool(key, value)?;
+                options
+            }
+        };
+
+        Ok(self)
+    }
+
+    pub(super) fn finish(mut self) -> Result<MssqlConnectOptions, Error> {
+        self.options.ssl_mode = self
+            .options
+            .ssl_mode
+            .with_encrypt(self.encrypt.as_deref(), self.trust_server_certificate)?;
+
+        Ok(self.options)
+    }
+}
+
+pub(super) fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T, Error> {
+    value.parse().map_err(|_| {
+        Error::Configuration(format!("invalid value {:?} for `{}`", value, key).into())
+    })
+}
+
+pub(super) fn parse_bool(key: &str, value: &str) -> Result<bool, Error> {
+    match &*value.to_ascii_lowercase() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+
+        _ => Err(Error::Configuration(
+            format!("invalid value {:?} for `{}`", value, key).into(),
+        )),
+    }
+}
@@ -1,15 +1,25 @@
+use log::LevelFilter;
 use sqlx_core::{connection::LogSettings, net::tls::CertificateInput};
 use std::env::var;
+use std::path::Path;
+use std::time::Duration;
 
+mod ado;
+mod application_intent;
 mod connect;
+mod jdbc;
+mod keyword;
 mod parse;
 mod ssl_mode;
+pub use application_intent::MssqlApplicationIntent;
 pub use ssl_mode::MssqlSslMode;
 
 #[derive(Debug, Clone)]
 pub struct MssqlConnectOptions {
     pub(crate) host: String,
     pub(crate) port: u16,
+    // whether the port was set, rather than to be looked up for a named instance
+    pub(crate) port_set: bool,
     pub(crate) username: String,
     pub(crate) database: String,
     pub(crate) password: Option<String>,
@@ -18,6 +28,17 @@ pub struct MssqlConnectOptions {
     pub(crate) ssl_root_cert: Option<CertificateInput>,
     pub(crate) ssl_client_cert: Option<CertificateInput>,
     pub(crate) ssl_client_key: Option<CertificateInput>,
+    pub(crate) ssl_host_name: Option<String>,
+    pub(crate) packet_size: u16,
+    pub(crate) info_log_level: LevelFilter,
+    pub(crate) app_name: String,
+    pub(crate) workstation_name: String,
+    pub(crate) language: String,
+    pub(crate) client_interface_name: String,
+    pub(crate) instance_name: Option<String>,
+    pub(crate) connect_timeout: Option<Duration>,
+    pub(crate) application_intent: MssqlApplicationIntent,
+    pub(crate) statement_cache_capacity: usize,
 }
 
 impl Default for MssqlConnectOptions {
@@ -30,6 +51,7 @@ impl MssqlConnectOptions {
     pub fn new() -> Self {
         Self {
             port: 1433,
+            port_set: false,
             host: String::from("localhost"),
             database: String::from("master"),
             username: String::from("sa"),
@@ -38,10 +60,21 @@ impl MssqlConnectOptions {
             ssl_root_cert: var("MSSSLROOTCERT").ok().map(CertificateInput::from),
             ssl_client_cert: var("MSSSLCERT").ok().map(CertificateInput::from),
             ssl_client_key: var("MSSSLKEY").ok().map(CertificateInput::from),
+            ssl_host_name: None,
             ssl_mode: var("MSSSLMODE")
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or_default(),
+            packet_size: 4096,
+            info_log_level: LevelFilter::Debug,
+            app_name: String::from(env!("CARGO_PKG_NAME")),
+            workstation_name: whoami::fallible::hostname().unwrap_or_default(),
+            language: String::new(),
+            client_interface_name: String::from(env!("CARGO_PKG_NAME")),
+            instance_name: None,
+            connect_timeout: None,
+            application_intent: Default::default(),
+            statement_cache_capacity: 1024,
         }
     }
 
@@ -52,6 +85,7 @@ impl MssqlConnectOptions {
 
     pub fn port(mut self, port: u16) -> Self {
         self.port = port;
+        self.port_set = true;
         self
     }
 
@@ -69,4 +103,277 @@ impl MssqlConnectOptions {
         self.database = database.to_owned();
         self
     }
+
+    /// Sets the size, in bytes, of the TDS packets requested from the server.
+    ///
+    /// Must be between 512 and 32767. The server may answer with a different size, which
+    /// is then used instead. The default is 4096.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::MssqlConnectOptions;
+    /// let options = MssqlConnectOptions::new()
+    ///     .packet_size(32767);
+    /// ```
+    pub fn packet_size(mut self, size: u16) -> Self {
+        self.packet_size = size;
+        self
+    }
+
+    /// Sets the application name sent to the server when logging in.
+    ///
+    /// It shows up as `program_name` in `sys.dm_exec_sessions` and as `APP_NAME()`. The
+    /// default is the name of this crate.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::MssqlConnectOptions;
+    /// let options = MssqlConnectOptions::new()
+    ///     .app_name("billing-service");
+    /// ```
+    pub fn app_name(mut self, name: &str) -> Self {
+        self.app_name = name.to_owned();
+        self
+    }
+
+    /// Sets the name of the client machine sent to the server when logging in.
+    ///
+    /// It shows up as `host_name` in `sys.dm_exec_sessions` and as `HOST_NAME()`. The
+    /// default is the host name of the machine.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::MssqlConnectOptions;
+    /// let options = MssqlConnectOptions::new()
+    ///     .workstation_name("billing-7f9c4");
+    /// ```
+    pub fn workstation_name(mut self, name: &str) -> Self {
+        self.workstation_name = name.to_owned();
+        self
+    }
+
+    /// Sets the language of the session, which decides the language of messages from the
+    /// server and the default date format.
+    ///
+    /// It is the name of one of the languages in `sys.syslanguages`. By default, the
+    /// default language of the login is used.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::MssqlConnectOptions;
+    /// let options = MssqlConnectOptions::new()
+    ///     .language("us_english");
+    /// ```
+    pub fn language(mut self, language: &str) -> Self {
+        self.language = language.to_owned();
+        self
+    }
+
+    /// Sets the name of the client library sent to the server when logging in.
+    ///
+    /// It shows up as `client_interface_name` in `sys.dm_exec_sessions`. The default is
+    /// the name of this crate, sent along with its version.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::MssqlConnectOptions;
+    /// let options = MssqlConnectOptions::new()
+    ///     .client_interface_name("welds");
+    /// ```
+    pub fn client_interface_name(mut self, name: &str) -> Self {
+        self.client_interface_name = name.to_owned();
+        self
+    }
+
+    /// Sets the name of the SQL Server instance to connect to.
+    ///
+    /// Unless a port is set with [`port`](Self::port), the port of the instance is looked up
+    /// from the SQL Server Browser service on the host, over UDP port 1434. The name is also
+    /// sent to the server, which refuses the connection if it is not that instance.
+    ///
+    /// The instance can be given in a URL as `mssql://host%5CSQLEXPRESS` or, as other
+    /// SQL Server clients take it, `mssql://host\SQLEXPRESS`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::MssqlConnectOptions;
+    /// let options = MssqlConnectOptions::new()
+    ///     .host("db.example.com")
+    ///     .instance_name("SQLEXPRESS");
+    /// ```
+    pub fn instance_name(mut self, name: &str) -> Self {
+        self.instance_name = Some(name.to_owned());
+        self
+    }
+
+    /// Sets the maximum time to wait for a connection to be established and logged in.
+    ///
+    /// By default, there is no limit beyond those of the operating system.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use welds_sqlx_mssql::MssqlConnectOptions;
+    /// let options = MssqlConnectOptions::new()
+    ///     .connect_timeout(Duration::from_secs(15));
+    /// ```
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the kind of workload declared to the server when logging in.
+    ///
+    /// The default is [`ReadWrite`](MssqlApplicationIntent::ReadWrite).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::{MssqlApplicationIntent, MssqlConnectOptions};
+    /// let options = MssqlConnectOptions::new()
+    ///     .application_intent(MssqlApplicationIntent::ReadOnly);
+    /// ```
+    pub fn application_intent(mut self, intent: MssqlApplicationIntent) -> Self {
+        self.application_intent = intent;
+        self
+    }
+
+    /// Sets the capacity of the connection's cache of prepared statements.
+    ///
+    /// The cache is used for [persistent](sqlx_core::executor::Execute::persistent) queries,
+    /// which is the default. Setting the capacity to 0 disables the cache.
+    ///
+    /// The default is 1024.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::MssqlConnectOptions;
+    /// let options = MssqlConnectOptions::new()
+    ///     .statement_cache_capacity(0);
+    /// ```
+    pub fn statement_cache_capacity(mut self, capacity: usize) -> Self {
+        self.statement_cache_capacity = capacity;
+        self
+    }
+
+    /// Sets the level informational messages from the server, such as the output of
+    /// `PRINT`, are logged at. They are logged under the `sqlx::mssql::info` target.
+    ///
+    /// The default is [`Debug`](LevelFilter::Debug). Use [`Off`](LevelFilter::Off) to not
+    /// log them at all; they are still handed to the connection's
+    /// [info handler](crate::MssqlConnection::set_info_handler).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use log::LevelFilter;
+    /// # use welds_sqlx_mssql::MssqlConnectOptions;
+    /// let options = MssqlConnectOptions::new()
+    ///     .log_info_messages(LevelFilter::Info);
+    /// ```
+    pub fn log_info_messages(mut self, level: LevelFilter) -> Self {
+        self.info_log_level = level;
+        self
+    }
+
+    /// Sets whether or with what priority a secure SSL TCP/IP connection will be negotiated
+    /// with the server.
+    ///
+    /// By default, the SSL mode is [`Prefer`](MssqlSslMode::Prefer), and the client will
+    /// first attempt an SSL connection but fallback to a non-SSL connection on failure.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::{MssqlSslMode, MssqlConnectOptions};
+    /// let options = MssqlConnectOptions::new()
+    ///     .ssl_mode(MssqlSslMode::Require);
+    /// ```
+    pub fn ssl_mode(mut self, mode: MssqlSslMode) -> Self {
+        self.ssl_mode = mode;
+        self
+    }
+
+    /// Sets the name of a file containing SSL certificate authority (CA) certificate(s).
+    /// If the file exists, the server's certificate will be verified to be signed by
+    /// one of these authorities.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::{MssqlSslMode, MssqlConnectOptions};
+    /// let options = MssqlConnectOptions::new()
+    ///     // Providing a CA certificate with less than VerifyCa is pointless
+    ///     .ssl_mode(MssqlSslMode::VerifyCa)
+    ///     .ssl_root_cert("./ca-certificate.crt");
+    /// ```
+    pub fn ssl_root_cert(mut self, cert: impl AsRef<Path>) -> Self {
+        self.ssl_root_cert = Some(CertificateInput::File(cert.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Sets the name of a file containing SSL client certificate.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::{MssqlSslMode, MssqlConnectOptions};
+    /// let options = MssqlConnectOptions::new()
+    ///     // Providing a client certificate with less than VerifyCa is pointless
+    ///     .ssl_mode(MssqlSslMode::VerifyCa)
+    ///     .ssl_client_cert("./client.crt");
+    /// ```
+    pub fn ssl_client_cert(mut self, cert: impl AsRef<Path>) -> Self {
+        self.ssl_client_cert = Some(CertificateInput::File(cert.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Sets the name of a file containing SSL client key.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::{MssqlSslMode, MssqlConnectOptions};
+    /// let options = MssqlConnectOptions::new()
+    ///     // Providing a client key with less than VerifyCa is pointless
+    ///     .ssl_mode(MssqlSslMode::VerifyCa)
+    ///     .ssl_client_key("./client.key");
+    /// ```
+    pub fn ssl_client_key(mut self, key: impl AsRef<Path>) -> Self {
+        self.ssl_client_key = Some(CertificateInput::File(key.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Sets the host name the server's certificate is verified against with
+    /// [`VerifyFull`](MssqlSslMode::VerifyFull) or [`Strict`](MssqlSslMode::Strict), when it
+    /// is not the host connected to, as with a connection through an IP address or an alias.
+    ///
+    /// This is `HostNameInCertificate` in the connection strings of other SQL Server clients.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use welds_sqlx_mssql::{MssqlSslMode, MssqlConnectOptions};
+    /// let options = MssqlConnectOptions::new()
+    ///     .host("10.0.0.5")
+    ///     .ssl_mode(MssqlSslMode::VerifyFull)
+    ///     .ssl_host_name("db.example.com");
+    /// ```
+    pub fn ssl_host_name(mut self, name: &str) -> Self {
+        self.ssl_host_name = Some(name.to_owned());
+        self
+    }
+
+    // the host name the server's certificate must be for
+    pub(crate) fn certificate_host(&self) -> &str {
+        self.ssl_host_name.as_deref().unwrap_or(&self.host)
+    }
 }
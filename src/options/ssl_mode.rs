@@ -4,15 +4,23 @@ use std::str::FromStr;
 /// Options for controlling the level of protection provided for Mssql SSL connections.
 ///
 /// It is used by the [`ssl_mode`](super::MssqlConnectOptions::ssl_mode) method.
-#[derive(Debug, Clone, Copy)]
+///
+/// TLS needs one of the `tls-rustls` or `tls-native-tls` features. Without either,
+/// `Allow` and `Prefer` connect in plain text, which fails if the server requires
+/// encryption, and the modes that require TLS fail to connect.
+#[derive(Debug, Clone, Copy, Default)]
 pub enum MssqlSslMode {
     /// Only try a non-SSL connection.
     Disable,
 
     /// First try a non-SSL connection; if that fails, try an SSL connection.
+    ///
+    /// The login packet, and with it the credentials, is still encrypted unless the
+    /// server does not support encryption at all.
     Allow,
 
     /// First try an SSL connection; if that fails, try a non-SSL connection.
+    #[default]
     Prefer,
 
     /// Only try an SSL connection. If a root CA file is present, verify the connection
@@ -26,12 +34,10 @@ pub enum MssqlSslMode {
     /// Only try an SSL connection; verify that the server certificate is issued by a trusted
     /// CA and that the requested server host name matches that in the certificate.
     VerifyFull,
-}
 
-impl Default for MssqlSslMode {
-    fn default() -> Self {
-        MssqlSslMode::Prefer
-    }
+    /// TDS 8.0 strict encryption. TLS is negotiated directly on the TCP socket, before
+    /// PRELOGIN, and the server certificate is verified as with `VerifyFull`.
+    Strict,
 }
 
 impl FromStr for MssqlSslMode {
@@ -45,6 +51,7 @@ impl FromStr for MssqlSslMode {
             "require" => MssqlSslMode::Require,
             "verify-ca" => MssqlSslMode::VerifyCa,
             "verify-full" => MssqlSslMode::VerifyFull,
+            "strict" => MssqlSslMode::Strict,
 
             _ => {
                 return Err(Error::Configuration(
@@ -54,3 +61,42 @@ impl FromStr for MssqlSslMode {
         })
     }
 }
+
+impl MssqlSslMode {
+    // the SSL mode for the `Encrypt` and `TrustServerCertificate` settings other SQL Server
+    // clients take, applied on top of the SSL mode set so far
+    pub(crate) fn with_encrypt(
+        self,
+        encrypt: Option<&str>,
+        trust_server_certificate: Option<bool>,
+    ) -> Result<Self, Error> {
+        let mode = match encrypt.map(str::to_ascii_lowercase).as_deref() {
+            None => self,
+
+            Some("true" | "yes" | "mandatory") => MssqlSslMode::VerifyFull,
+            Some("false" | "no" | "optional") => MssqlSslMode::Allow,
+            Some("strict") => MssqlSslMode::Strict,
+
+            Some(_) => {
+                return Err(Error::Configuration(
+                    format!(
+                        "unknown value {:?} for `encrypt`",
+                        encrypt.unwrap_or_default()
+                    )
+                    .into(),
+                ));
+            }
+        };
+
+        Ok(match mode {
+            // encrypt, but take whatever certificate the server has
+            MssqlSslMode::VerifyCa | MssqlSslMode::VerifyFull
+                if trust_server_certificate == Some(true) =>
+            {
+                MssqlSslMode::Require
+            }
+
+            mode => mode,
+        })
+    }
+}
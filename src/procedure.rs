@@ -0,0 +1,311 @@
+use crate::arguments::param_name;
+use crate::decode::Decode;
+use crate::encode::Encode;
+use crate::error::Error;
+use crate::logger::QueryLogger;
+use crate::protocol::done::Status;
+use crate::protocol::message::Message;
+use crate::protocol::packet::PacketType;
+use crate::protocol::rpc::{OptionFlags, RpcRequest};
+use crate::protocol::type_info::DataType;
+use crate::value::{Value, ValueRef};
+use crate::{
+    Mssql, MssqlArguments, MssqlConnection, MssqlQueryResult, MssqlRow, MssqlTypeInfo, MssqlValue,
+};
+use either::Either;
+use futures_core::stream::BoxStream;
+use futures_util::TryStreamExt;
+use sqlx_core::error::mismatched_types;
+use sqlx_core::types::Type;
+use std::sync::Arc;
+
+/// A call to a stored procedure, sent as an RPC request rather than as an `EXEC` statement.
+///
+/// Parameters are passed by name. After the call has run, the values of its `OUTPUT`
+/// parameters and its `RETURN` status can be read from it.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use futures_util::TryStreamExt;
+/// # use welds_sqlx_mssql::{MssqlConnection, MssqlProcedureCall};
+/// # async fn example(conn: &mut MssqlConnection) -> Result<(), sqlx_core::Error> {
+/// let mut call = MssqlProcedureCall::new("dbo.create_order")
+///     .bind("@customer_id", 42_i32)
+///     .bind_output("@order_id", None::<i32>);
+///
+/// // rows of any result sets the procedure selects are streamed as they arrive
+/// let mut results = call.fetch_many(conn);
+///
+/// while let Some(result) = results.try_next().await? {
+///     // ...
+/// }
+///
+/// drop(results);
+///
+/// let order_id: Option<i32> = call.output("@order_id")?;
+/// let status = call.return_status();
+/// # Ok(())
+/// # }
+/// ```
+pub struct MssqlProcedureCall {
+    name: String,
+    arguments: MssqlArguments,
+    outputs: Vec<(String, MssqlValue)>,
+    return_status: Option<i32>,
+}
+
+impl MssqlProcedureCall {
+    /// Creates a call to the stored procedure with the given (optionally schema-qualified) name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            arguments: MssqlArguments::default(),
+            outputs: Vec::new(),
+            return_status: None,
+        }
+    }
+
+    /// Binds a value to the input parameter with the given name, such as `@id`.
+    pub fn bind<'q, T>(mut self, name: &str, value: T) -> Self
+    where
+        T: 'q + Encode<'q, Mssql> + Type<Mssql>,
+    {
+        let name = param_name(name);
+
+        self.arguments.check_name(&name);
+        self.arguments.add_param(&name, value);
+        self
+    }
+
+    /// Binds an `OUTPUT` parameter with the given name, such as `@total`, and the value
+    /// it starts out with.
+    ///
+    /// The parameter is declared as `T`; variable-length types are declared at their
+    /// maximum length, e.g. `nvarchar(max)`, so the value the procedure sets is never cut short.
+    /// Read it back with [`output`][Self::output] once the call has run.
+    pub fn bind_output<'q, T>(mut self, name: &str, value: Option<T>) -> Self
+    where
+        T: 'q + Encode<'q, Mssql> + Type<Mssql>,
+    {
+        let mut ty = value
+            .as_ref()
+            .and_then(Encode::produces)
+            .unwrap_or_else(T::type_info);
+
+        if matches!(
+            ty.0.ty,
+            DataType::NVarChar | DataType::BigVarChar | DataType::BigVarBinary
+        ) {
+            ty.0.size = 0xFFFF;
+        }
+
+        let name = param_name(name);
+
+        self.arguments.check_name(&name);
+        self.arguments.declare_as(&name, ty, value);
+        self
+    }
+
+    /// Runs the procedure, returning a stream of the results and rows of every statement
+    /// the procedure runs.
+    ///
+    /// The `OUTPUT` parameters and `RETURN` status are available once the stream
+    /// has been read to the end.
+    pub fn fetch_many<'e>(
+        &'e mut self,
+        conn: &'e mut MssqlConnection,
+    ) -> BoxStream<'e, Result<Either<MssqlQueryResult, MssqlRow>, Error>> {
+        self.outputs.clear();
+        self.return_status = None;
+
+        Box::pin(try_stream! {
+            let mut logger = QueryLogger::new(&self.name, conn.log_settings.clone());
+
+            self.arguments.check("")?;
+
+            conn.stream.wait_until_ready().await?;
+            conn.stream.pending_done_count += 1;
+
+            conn.stream.write_packet(
+                PacketType::Rpc,
+                RpcRequest {
+                    transaction_descriptor: conn.stream.transaction_descriptor,
+                    arguments: &self.arguments,
+                    procedure: Either::Left(&self.name),
+                    options: OptionFlags::empty(),
+                },
+            );
+
+            conn.stream.flush().await?;
+
+            loop {
+                let message = conn.stream.recv_message().await?;
+
+                match message {
+                    Message::Row(row) => {
+                        let columns = Arc::clone(&conn.stream.columns);
+                        let column_names = Arc::clone(&conn.stream.column_names);
+
+                        logger.increment_rows_returned();
+
+                        r#yield!(Either::Right(MssqlRow { row, column_names, columns }));
+                    }
+
+                    Message::ReturnValue(value) => {
+                        self.outputs.push((
+                            value.param_name,
+                            MssqlValue {
+                                type_info: MssqlTypeInfo(value.type_info),
+                                data: value.value,
+                            },
+                        ));
+                    }
+
+                    Message::ReturnStatus(status) => {
+                        self.return_status = Some(status.value);
+                    }
+
+                    Message::Done(done) | Message::DoneProc(done) => {
+                        if !done.status.contains(Status::DONE_MORE) {
+                            conn.stream.handle_done(&done);
+                        }
+
+                        if done.status.contains(Status::DONE_COUNT) {
+                            let rows_affected = done.affected_rows;
+                            logger.increase_rows_affected(rows_affected);
+                            r#yield!(Either::Left(MssqlQueryResult {
+                                rows_affected,
+                            }));
+                        }
+
+                        if !done.status.contains(Status::DONE_MORE) {
+                            break;
+                        }
+                    }
+
+                    Message::DoneInProc(done) if done.status.contains(Status::DONE_COUNT) => {
+                        let rows_affected = done.affected_rows;
+                        logger.increase_rows_affected(rows_affected);
+                        r#yield!(Either::Left(MssqlQueryResult {
+                            rows_affected,
+                        }));
+                    }
+
+                    _ => {}
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Runs the procedure, returning the rows of every result set it selects.
+    pub async fn fetch_all(&mut self, conn: &mut MssqlConnection) -> Result<Vec<MssqlRow>, Error> {
+        self.fetch_many(conn)
+            .try_filter_map(|step| async move { Ok(step.right()) })
+            .try_collect()
+            .await
+    }
+
+    /// Runs the procedure, discarding any rows, and returns the number of rows affected by
+    /// the statements it ran.
+    pub async fn execute(&mut self, conn: &mut MssqlConnection) -> Result<MssqlQueryResult, Error> {
+        self.fetch_many(conn)
+            .try_filter_map(|step| async move { Ok(step.left()) })
+            .try_collect()
+            .await
+    }
+
+    /// Decodes the value the procedure set for the `OUTPUT` parameter with the given name,
+    /// compared case-insensitively as SQL Server does.
+    pub fn output<'r, T>(&'r self, name: &str) -> Result<T, Error>
+    where
+        T: Decode<'r, Mssql> + Type<Mssql>,
+    {
+        let name = param_name(name);
+
+        let value = self
+            .outputs
+            .iter()
+            .find(|(output, _)| output.eq_ignore_ascii_case(&name))
+            .map(|(_, value)| value.as_ref())
+            .ok_or_else(|| Error::ColumnNotFound(name.clone()))?;
+
+        if !value.is_null() {
+            let ty = value.type_info();
+
+            if !T::compatible(&ty) {
+                return Err(Error::ColumnDecode {
+                    index: name,
+                    source: mismatched_types::<Mssql, T>(&ty),
+                });
+            }
+        }
+
+        T::decode(value).map_err(|source| Error::ColumnDecode {
+            index: name,
+            source,
+        })
+    }
+
+    /// The value of the procedure's `RETURN` statement, once it has run.
+    pub fn return_status(&self) -> Option<i32> {
+        self.return_status
+    }
+}
+
+#[test]
+fn test_procedure_call() {
+    use crate::connection::block_on;
+    use crate::connection::{tabular_result, written_messages};
+
+    let mut response = vec![0x79, 5, 0, 0, 0]; // RETURNSTATUS of 5
+
+    // RETURNVALUE of 42 for @b
+    response.extend(&[0xac, 1, 0, 2, b'@', 0, b'b', 0, 1, 0, 0, 0, 0, 0, 0]);
+    response.extend(&[0x26, 4, 4, 42, 0, 0, 0]);
+
+    response.extend(&[0xfe, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]); // DONEPROC
+
+    let (mut conn, written) = MssqlConnection::mock(tabular_result(&response));
+
+    let mut call = MssqlProcedureCall::new("p")
+        .bind("a", 1_i32)
+        .bind_output("@b", None::<i32>);
+
+    block_on(call.execute(&mut conn)).unwrap();
+
+    assert_eq!(call.return_status(), Some(5));
+    assert_eq!(call.output::<Option<i32>>("b").unwrap(), Some(42));
+    assert_eq!(call.output::<Option<i32>>("@B").unwrap(), Some(42));
+    assert!(call.output::<i32>("@c").is_err());
+
+    let messages = written_messages(&written.lock().unwrap());
+    let (ty, payload) = &messages[0];
+    let headers_len = u32::from_le_bytes(payload[..4].try_into().unwrap()) as usize;
+
+    assert_eq!(*ty, PacketType::Rpc as u8);
+
+    #[rustfmt::skip]
+    assert_eq!(&payload[headers_len..], [
+        // the procedure name, and no option flags
+        1, 0, b'p', 0, 0, 0,
+        // @a, an INT of 1
+        2, b'@', 0, b'a', 0, 0, 0x26, 4, 4, 1, 0, 0, 0,
+        // @b, an OUTPUT INT that starts out NULL
+        2, b'@', 0, b'b', 0, 1, 0x26, 4, 0,
+    ]);
+}
+
+#[test]
+fn test_procedure_call_checks_arguments() {
+    use crate::connection::block_on;
+
+    let (mut conn, written) = MssqlConnection::mock(Vec::new());
+
+    let mut call = MssqlProcedureCall::new("p").bind("not a name", 1_i32);
+
+    assert!(block_on(call.execute(&mut conn)).is_err());
+    assert!(written.lock().unwrap().is_empty());
+}
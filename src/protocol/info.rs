@@ -3,16 +3,15 @@ use bytes::{Buf, Bytes};
 use crate::error::Error;
 use crate::io::MssqlBufExt;
 
-#[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct Info {
-    pub(crate) number: u32,
+    pub(crate) number: i32,
     pub(crate) state: u8,
     pub(crate) class: u8,
     pub(crate) message: String,
     pub(crate) server: String,
     pub(crate) procedure: String,
-    pub(crate) line: u32,
+    pub(crate) line: i32,
 }
 
 impl Info {
@@ -20,13 +19,13 @@ impl Info {
         let len = buf.get_u16_le();
         let mut data = buf.split_to(len as usize);
 
-        let number = data.get_u32_le();
+        let number = data.get_i32_le();
         let state = data.get_u8();
         let class = data.get_u8();
         let message = data.get_us_varchar()?;
         let server = data.get_b_varchar()?;
         let procedure = data.get_b_varchar()?;
-        let line = data.get_u32_le();
+        let line = data.get_i32_le();
 
         Ok(Self {
             number,
@@ -16,6 +16,7 @@ pub struct Login7<'a> {
     pub language: &'a str,
     pub database: &'a str,
     pub client_id: [u8; 6],
+    pub read_only_intent: bool,
 }
 
 impl Encode<'_> for Login7<'_> {
@@ -59,10 +60,14 @@ impl Encode<'_> for Login7<'_> {
         buf.push(0b00_00_00_11);
 
         // [TypeFlags]
-        //    2 | <fReadOnlyIntent>
-        //    1 | OLEDB_OFF (0)
-        //    0 | SQL_DFLT (0)
-        buf.push(0);
+        //    5 | <fReadOnlyIntent>
+        //    4 | OLEDB_OFF (0)
+        //  3-0 | SQL_DFLT (0)
+        buf.push(if self.read_only_intent {
+            0b00_10_00_00
+        } else {
+            0
+        });
 
         // [OptionFlags3]
         //    4 | <fExtension>
@@ -97,9 +102,8 @@ impl Encode<'_> for Login7<'_> {
         // password buffer starting with the position pointed to by ibPassword or
         // ibChangePassword, the client SHOULD first swap the four high bits with
         // the four low bits and then do a bit-XOR with 0xA5 (10100101).
-        for i in password_start..buf.len() {
-            let b = buf[i];
-            buf[i] = ((b << 4) & 0xf0 | (b >> 4) & 0x0f) ^ 0xa5;
+        for b in &mut buf[password_start..] {
+            *b = ((*b << 4) & 0xf0 | (*b >> 4) & 0x0f) ^ 0xa5;
         }
 
         // [AppName] The client application name
@@ -143,7 +147,7 @@ impl Encode<'_> for Login7<'_> {
     }
 }
 
-fn write_offset(buf: &mut Vec<u8>, offsets: &mut usize, beg: usize) {
+fn write_offset(buf: &mut [u8], offsets: &mut usize, beg: usize) {
     // The offset must be relative to the beginning of the packet payload, after
     // the packet header
 
@@ -183,6 +187,7 @@ fn test_encode_login() {
         language: "",
         database: "",
         client_id: [0x00, 0x50, 0x8B, 0xE2, 0xB7, 0x8F],
+        read_only_intent: false,
     };
 
     // Adapted from v20191101 of MS-TDS
@@ -2,7 +2,6 @@ use bytes::{Buf, Bytes};
 
 use crate::protocol::done::Done;
 use crate::protocol::login_ack::LoginAck;
-use crate::protocol::order::Order;
 use crate::protocol::return_status::ReturnStatus;
 use crate::protocol::return_value::ReturnValue;
 use crate::protocol::row::Row;
@@ -16,7 +15,6 @@ pub(crate) enum Message {
     Row(Row),
     ReturnStatus(ReturnStatus),
     ReturnValue(ReturnValue),
-    Order(Order),
 }
 
 #[derive(Debug)]
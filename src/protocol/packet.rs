@@ -25,6 +25,10 @@ pub(crate) struct PacketHeader {
     pub(crate) packet_id: u8,
 }
 
+impl PacketHeader {
+    pub(crate) const SIZE: usize = 8;
+}
+
 impl<'s> Encode<'s, &'s mut usize> for PacketHeader {
     fn encode_with(&self, buf: &mut Vec<u8>, offset: &'s mut usize) {
         buf.push(self.r#type as u8);
@@ -41,6 +45,55 @@ impl<'s> Encode<'s, &'s mut usize> for PacketHeader {
     }
 }
 
+// A complete message, split into as many packets as it takes to fit into the negotiated
+// packet size.
+pub(crate) struct Packets<'a> {
+    pub(crate) r#type: PacketType,
+
+    // The encoded message, without any packet headers.
+    pub(crate) payload: &'a [u8],
+
+    // The maximum size of a packet, including its header.
+    pub(crate) packet_size: usize,
+
+    // Whether the payload is the end of the message. Otherwise it must fill whole packets
+    // and more of the message follows.
+    pub(crate) end_of_message: bool,
+}
+
+impl Encode<'_> for Packets<'_> {
+    fn encode_with(&self, buf: &mut Vec<u8>, _: ()) {
+        let max_data_size = self.packet_size - PacketHeader::SIZE;
+
+        // an empty message still takes one (empty) packet
+        let num_packets = self.payload.len().div_ceil(max_data_size).max(1);
+
+        let mut chunks = self.payload.chunks(max_data_size);
+
+        for i in 0..num_packets {
+            let chunk = chunks.next().unwrap_or_default();
+
+            let status = if self.end_of_message && i + 1 == num_packets {
+                Status::END_OF_MESSAGE
+            } else {
+                Status::NORMAL
+            };
+
+            PacketHeader {
+                r#type: self.r#type,
+                status,
+                length: (chunk.len() + PacketHeader::SIZE) as u16,
+                server_process_id: 0,
+                // starts at 1 and wraps around
+                packet_id: ((i + 1) % 256) as u8,
+            }
+            .encode_with(buf, &mut 0);
+
+            buf.extend_from_slice(chunk);
+        }
+    }
+}
+
 impl Decode<'_> for PacketHeader {
     fn decode_with(mut buf: Bytes, _: ()) -> Result<Self, Error> {
         Ok(Self {
@@ -136,3 +189,30 @@ bitflags! {
         const RESET_CONN_SKIP_TRAN = 0x10;
     }
 }
+
+#[test]
+fn test_encode_packets() {
+    let mut buf = Vec::new();
+
+    let payload: Vec<u8> = (0..20).collect();
+
+    Packets {
+        r#type: PacketType::SqlBatch,
+        payload: &payload,
+        packet_size: 16,
+        end_of_message: true,
+    }
+    .encode(&mut buf);
+
+    #[rustfmt::skip]
+    let expected = vec![
+        0x01, 0x00, 0x00, 0x10, 0x00, 0x00, 0x01, 0x00,
+        0, 1, 2, 3, 4, 5, 6, 7,
+        0x01, 0x00, 0x00, 0x10, 0x00, 0x00, 0x02, 0x00,
+        8, 9, 10, 11, 12, 13, 14, 15,
+        0x01, 0x01, 0x00, 0x0c, 0x00, 0x00, 0x03, 0x00,
+        16, 17, 18, 19,
+    ];
+
+    assert_eq!(expected, buf);
+}
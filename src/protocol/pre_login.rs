@@ -18,15 +18,17 @@ pub(crate) struct PreLogin<'a> {
     pub(crate) thread_id: Option<u32>,
     pub(crate) trace_id: Option<TraceId>,
     pub(crate) multiple_active_result_sets: Option<bool>,
+    // set when decoding the answer of a server that is not the instance sent to it
+    pub(crate) instance_mismatch: bool,
 }
 
 impl<'de> Decode<'de> for PreLogin<'de> {
     fn decode_with(buf: Bytes, _: ()) -> Result<Self, Error> {
         let mut version = None;
         let mut encryption = None;
+        let mut instance_mismatch = false;
 
         // TODO: Decode the remainder of the structure
-        // let mut instance = None;
         // let mut thread_id = None;
         // let mut trace_id = None;
         // let mut multiple_active_result_sets = None;
@@ -61,7 +63,14 @@ impl<'de> Decode<'de> for PreLogin<'de> {
                             encryption = Some(Encrypt::from_bits_truncate(data.get_u8()));
                         }
 
-                        tok => todo!("{:?}", tok),
+                        // the server answers with 0x01 if it is not the instance that was sent
+                        PreLoginOptionToken::Instance => {
+                            instance_mismatch = data.first() == Some(&0x01);
+                        }
+
+                        // servers answer with THREADID and MARS as well;
+                        // nothing in those affects the connection so far
+                        _ => {}
                     }
                 }
 
@@ -73,8 +82,7 @@ impl<'de> Decode<'de> for PreLogin<'de> {
                     return Err(err_protocol!(
                         "PRELOGIN: unexpected login option token: 0x{:02?}",
                         token
-                    )
-                    .into());
+                    ));
                 }
             }
         }
@@ -89,6 +97,7 @@ impl<'de> Decode<'de> for PreLogin<'de> {
         Ok(Self {
             version,
             encryption,
+            instance_mismatch,
 
             ..Default::default()
         })
@@ -115,7 +124,7 @@ impl Encode<'_> for PreLogin<'_> {
         // Calculate the length of the option offset block. Each block is 5 bytes and it ends in
         // a 1 byte terminator.
         let len_offsets = (num_options * 5) + 1;
-        let mut offsets = buf.len() as usize;
+        let mut offsets = buf.len();
         let mut offset = len_offsets as u16;
 
         // Reserve a chunk for the offset block and set the final terminator
@@ -171,7 +180,7 @@ enum PreLoginOptionToken {
 }
 
 impl PreLoginOptionToken {
-    fn put(self, buf: &mut Vec<u8>, pos: &mut usize, offset: &mut u16, len: u16) {
+    fn put(self, buf: &mut [u8], pos: &mut usize, offset: &mut u16, len: u16) {
         buf[*pos] = self as u8;
         *pos += 1;
 
@@ -243,6 +252,9 @@ bitflags! {
     /// wire encryption to be used.
     #[derive(Default)]
     pub(crate) struct Encrypt: u8 {
+        /// Encryption is available but off; only the LOGIN7 packet is encrypted.
+        const OFF = 0x00;
+
         /// Encryption is available but on.
         const ON = 0x01;
 
@@ -307,5 +319,5 @@ fn test_decode_pre_login() {
     assert_eq!(pre_login.version.sub_build, 0);
 
     // ENCRYPT_OFF
-    assert_eq!(pre_login.encryption.bits(), 0);
+    assert_eq!(pre_login.encryption, Encrypt::OFF);
 }
@@ -12,7 +12,7 @@ use crate::protocol::type_info::TypeInfo;
 #[derive(Debug)]
 pub(crate) struct ReturnValue {
     param_ordinal: u16,
-    param_name: String,
+    pub(crate) param_name: String,
     status: ReturnValueStatus,
     user_type: u32,
     flags: Flags,
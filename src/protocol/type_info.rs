@@ -1,8 +1,9 @@
 use bitflags::bitflags;
-use bytes::{Buf, Bytes};
+use bytes::{Buf, Bytes, BytesMut};
 use encoding_rs::Encoding;
 
 use crate::error::Error;
+use crate::io::{MssqlBufExt, MssqlBufMutExt};
 use crate::Mssql;
 use sqlx_core::encode::{Encode, IsNull};
 
@@ -84,8 +85,18 @@ pub(crate) enum DataType {
     Image = 0x22,
     NText = 0x63,
     Variant = 0x62,
+
+    // table-valued parameters
+    // https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-tds/c264db71-c1ec-4fe8-b5ef-19d54b1e6566
+    Tvp = 0xf3,
 }
 
+// the total length of a PLP value that is NULL
+pub(crate) const PLP_NULL: u64 = 0xFFFF_FFFF_FFFF_FFFF;
+
+// the total length of a PLP value sent without knowing its length up front
+pub(crate) const PLP_UNKNOWN_LEN: u64 = 0xFFFF_FFFF_FFFF_FFFE;
+
 // http://msdn.microsoft.com/en-us/library/dd358284.aspx
 #[derive(Debug, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "offline", derive(serde::Serialize, serde::Deserialize))]
@@ -95,6 +106,8 @@ pub(crate) struct TypeInfo {
     pub(crate) scale: u8,
     pub(crate) precision: u8,
     pub(crate) collation: Option<Collation>,
+    // the name of the user-defined table type of a table-valued parameter
+    pub(crate) type_name: Option<String>,
 }
 
 impl TypeInfo {
@@ -105,12 +118,67 @@ impl TypeInfo {
             scale: 0,
             precision: 0,
             collation: None,
+            type_name: None,
+        }
+    }
+
+    // a DECIMAL(p, s) sized to hold the given precision
+    // https://docs.microsoft.com/en-us/sql/t-sql/data-types/decimal-and-numeric-transact-sql
+    pub(crate) const fn decimal(precision: u8, scale: u8) -> Self {
+        // 1 byte for the sign, followed by 4, 8, 12 or 16 bytes of magnitude
+        let size = match precision {
+            0..=9 => 5,
+            10..=19 => 9,
+            20..=28 => 13,
+            _ => 17,
+        };
+
+        Self {
+            ty: DataType::DecimalN,
+            size,
+            scale,
+            precision,
+            collation: None,
+            type_name: None,
+        }
+    }
+
+    // a TIME, DATETIME2 or DATETIMEOFFSET with the given fractional seconds scale (0 to 7)
+    pub(crate) const fn time(ty: DataType, scale: u8) -> Self {
+        // the time part is 3, 4 or 5 bytes depending on the scale
+        let mut size = match scale {
+            0..=2 => 3,
+            3 | 4 => 4,
+            _ => 5,
+        };
+
+        match ty {
+            // followed by a 3-byte date
+            DataType::DateTime2N => {
+                size += 3;
+            }
+
+            // followed by a 3-byte date and a 2-byte offset
+            DataType::DateTimeOffsetN => {
+                size += 5;
+            }
+
+            _ => {}
+        }
+
+        Self {
+            ty,
+            size,
+            scale,
+            precision: 0,
+            collation: None,
+            type_name: None,
         }
     }
 
     pub(crate) fn encoding(&self) -> Result<&'static Encoding, Error> {
         match self.ty {
-            DataType::NChar | DataType::NVarChar => Ok(encoding_rs::UTF_16LE),
+            DataType::NChar | DataType::NVarChar | DataType::Xml => Ok(encoding_rs::UTF_16LE),
 
             DataType::VarChar | DataType::Char | DataType::BigChar | DataType::BigVarChar => {
                 // unwrap: impossible to unwrap here, collation will be set
@@ -133,6 +201,69 @@ impl TypeInfo {
         }
     }
 
+    // the type described by a row of [sp_describe_first_result_set] or
+    // [sp_describe_undeclared_parameters], as it would be sent for a nullable column
+    // https://docs.microsoft.com/en-us/sql/relational-databases/system-catalog-views/sys-types-transact-sql
+    pub(crate) fn from_system_type(
+        id: i32,
+        max_length: i16,
+        precision: u8,
+        scale: u8,
+    ) -> Result<Self, Error> {
+        // -1 is reported for the (MAX) types
+        let size = if max_length < 0 {
+            0xFFFF
+        } else {
+            max_length as u32
+        };
+
+        Ok(match id {
+            48 => Self::new(DataType::IntN, 1),
+            52 => Self::new(DataType::IntN, 2),
+            56 => Self::new(DataType::IntN, 4),
+            127 => Self::new(DataType::IntN, 8),
+            104 => Self::new(DataType::BitN, 1),
+            59 | 62 => Self::new(DataType::FloatN, size),
+            60 | 122 => Self::new(DataType::MoneyN, size),
+            58 | 61 => Self::new(DataType::DateTimeN, size),
+            40 => Self::new(DataType::DateN, 3),
+            41 => Self::time(DataType::TimeN, scale),
+            42 => Self::time(DataType::DateTime2N, scale),
+            43 => Self::time(DataType::DateTimeOffsetN, scale),
+            36 => Self::new(DataType::Guid, 16),
+
+            106 | 108 => Self {
+                ty: if id == 106 {
+                    DataType::DecimalN
+                } else {
+                    DataType::NumericN
+                },
+                size,
+                scale,
+                precision,
+                collation: None,
+                type_name: None,
+            },
+
+            165 => Self::new(DataType::BigVarBinary, size),
+            173 | 189 => Self::new(DataType::BigBinary, size),
+            167 => Self::new(DataType::BigVarChar, size),
+            175 => Self::new(DataType::BigChar, size),
+            231 => Self::new(DataType::NVarChar, size),
+            239 => Self::new(DataType::NChar, size),
+            241 => Self::new(DataType::Xml, 0xFFFF),
+            34 => Self::new(DataType::Image, size),
+            35 => Self::new(DataType::Text, size),
+            99 => Self::new(DataType::NText, size),
+            98 => Self::new(DataType::Variant, size),
+            240 => Self::new(DataType::UserDefined, size),
+
+            _ => {
+                return Err(err_protocol!("unknown system type id {}", id));
+            }
+        })
+    }
+
     // reads a TYPE_INFO from the buffer
     pub(crate) fn get(buf: &mut Bytes) -> Result<Self, Error> {
         let ty = DataType::get(buf)?;
@@ -157,35 +288,11 @@ impl TypeInfo {
             DataType::TimeN | DataType::DateTime2N | DataType::DateTimeOffsetN => {
                 let scale = buf.get_u8();
 
-                let mut size = match scale {
-                    0 | 1 | 2 => 3,
-                    3 | 4 => 4,
-                    5 | 6 | 7 => 5,
-
-                    scale => {
-                        return Err(err_protocol!("invalid scale {} for type {:?}", scale, ty));
-                    }
-                };
-
-                match ty {
-                    DataType::DateTime2N => {
-                        size += 3;
-                    }
-
-                    DataType::DateTimeOffsetN => {
-                        size += 5;
-                    }
-
-                    _ => {}
+                if scale > 7 {
+                    return Err(err_protocol!("invalid scale {} for type {:?}", scale, ty));
                 }
 
-                Self {
-                    scale,
-                    size,
-                    ty,
-                    precision: 0,
-                    collation: None,
-                }
+                Self::time(ty, scale)
             }
 
             DataType::Guid
@@ -197,7 +304,25 @@ impl TypeInfo {
             | DataType::Char
             | DataType::VarChar
             | DataType::Binary
-            | DataType::VarBinary => Self::new(ty, buf.get_u8() as u32),
+            | DataType::VarBinary => {
+                let size = buf.get_u8() as u32;
+
+                let valid = match ty {
+                    DataType::Guid => size == 16,
+                    DataType::IntN => matches!(size, 1 | 2 | 4 | 8),
+                    DataType::BitN => size == 1,
+                    DataType::FloatN | DataType::MoneyN | DataType::DateTimeN => {
+                        matches!(size, 4 | 8)
+                    }
+                    _ => true,
+                };
+
+                if !valid {
+                    return Err(err_protocol!("invalid size {} for type {:?}", size, ty));
+                }
+
+                Self::new(ty, size)
+            }
 
             DataType::Decimal | DataType::Numeric | DataType::DecimalN | DataType::NumericN => {
                 let size = buf.get_u8() as u32;
@@ -210,6 +335,7 @@ impl TypeInfo {
                     scale,
                     ty,
                     collation: None,
+                    type_name: None,
                 }
             }
 
@@ -225,7 +351,21 @@ impl TypeInfo {
                     collation: Some(collation),
                     scale: 0,
                     precision: 0,
+                    type_name: None,
+                }
+            }
+
+            DataType::Xml => {
+                // an XML schema collection the column is bound to
+                // we do not validate against it so it is only skipped over
+                if buf.get_u8() != 0 {
+                    let _db_name = buf.get_b_varchar()?;
+                    let _owning_schema = buf.get_b_varchar()?;
+                    let _xml_schema_collection = buf.get_us_varchar()?;
                 }
+
+                // always sent as PLP
+                Self::new(ty, 0xFFFF)
             }
 
             _ => {
@@ -285,20 +425,50 @@ impl TypeInfo {
             DataType::BigVarChar | DataType::BigChar | DataType::NVarChar | DataType::NChar => {
                 buf.extend(&(self.size as u16).to_le_bytes());
 
-                if let Some(collation) = &self.collation {
-                    collation.put(buf);
-                } else {
-                    buf.extend(&0_u32.to_le_bytes());
-                    buf.push(0);
-                }
+                self.put_collation(buf);
             }
 
-            _ => {
-                unimplemented!("unsupported data type {:?}", self.ty);
+            DataType::Text | DataType::NText => {
+                buf.extend(&self.size.to_le_bytes());
+                self.put_collation(buf);
+            }
+
+            DataType::Image | DataType::Variant => {
+                buf.extend(&self.size.to_le_bytes());
+            }
+
+            DataType::Xml => {
+                // no XML schema collection
+                buf.push(0);
+            }
+
+            DataType::UserDefined => {
+                // never sent; a parameter of this type fails to be declared before
+                // the request is written
+            }
+
+            DataType::Tvp => {
+                // [TVP_TYPENAME]; the database name must be empty
+                // the column metadata follows as part of the value
+                let type_name = self.type_name.as_deref().unwrap_or_default();
+                let (schema, name) = type_name.rsplit_once('.').unwrap_or(("", type_name));
+
+                buf.put_b_varchar("");
+                buf.put_b_varchar(unquote(schema));
+                buf.put_b_varchar(unquote(name));
             }
         }
     }
 
+    fn put_collation(&self, buf: &mut Vec<u8>) {
+        if let Some(collation) = &self.collation {
+            collation.put(buf);
+        } else {
+            buf.extend(&0_u32.to_le_bytes());
+            buf.push(0);
+        }
+    }
+
     pub(crate) fn is_null(&self) -> bool {
         matches!(self.ty, DataType::Null)
     }
@@ -359,6 +529,11 @@ impl TypeInfo {
             | DataType::NChar
             | DataType::Xml
             | DataType::UserDefined => {
+                if self.size == 0xFFFF {
+                    // a MAX type
+                    return Self::get_plp_value(buf);
+                }
+
                 let size = buf.get_u16_le();
 
                 if size == 0xFF_FF {
@@ -377,10 +552,46 @@ impl TypeInfo {
                     Some(buf.split_to(size as usize))
                 }
             }
+
+            // only ever sent as a parameter; a TYPE_INFO of this type is rejected when read
+            DataType::Tvp => None,
         }
     }
 
-    pub(crate) fn put_value<'q, T: Encode<'q, Mssql>>(&self, buf: &mut Vec<u8>, value: T) {
+    // reads a PLP (partially length-prefixed) value, a total length followed by chunks
+    // until an empty one
+    // https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-tds/3f983fde-0509-485a-8c40-a9fa6679a828
+    fn get_plp_value(buf: &mut Bytes) -> Option<Bytes> {
+        let len = buf.get_u64_le();
+
+        if len == PLP_NULL {
+            return None;
+        }
+
+        let mut value = BytesMut::new();
+
+        if len != PLP_UNKNOWN_LEN {
+            value.reserve(len as usize);
+        }
+
+        loop {
+            let chunk_len = buf.get_u32_le() as usize;
+
+            if chunk_len == 0 {
+                break;
+            }
+
+            value.extend_from_slice(&buf.split_to(chunk_len));
+        }
+
+        Some(value.freeze())
+    }
+
+    pub(crate) fn put_value<'q, T: Encode<'q, Mssql>>(
+        &self,
+        buf: &mut Vec<u8>,
+        value: T,
+    ) -> Result<(), String> {
         match self.ty {
             DataType::Null
             | DataType::TinyInt
@@ -415,7 +626,7 @@ impl TypeInfo {
             | DataType::VarChar
             | DataType::Binary
             | DataType::VarBinary => {
-                self.put_byte_len_value(buf, value);
+                return self.put_byte_len_value(buf, value);
             }
 
             DataType::BigVarBinary
@@ -426,30 +637,73 @@ impl TypeInfo {
             | DataType::NChar
             | DataType::Xml
             | DataType::UserDefined => {
-                self.put_short_len_value(buf, value);
+                if self.size == 0xFFFF {
+                    // a MAX type
+                    self.put_plp_value(buf, value);
+                } else {
+                    self.put_short_len_value(buf, value);
+                }
             }
 
             DataType::Text | DataType::Image | DataType::NText | DataType::Variant => {
                 self.put_long_len_value(buf, value);
             }
+
+            DataType::Tvp => {
+                self.put_table_value(buf, value);
+            }
         }
+
+        Ok(())
+    }
+
+    // the date types write nothing at all for a date SQL Server cannot store, and DECIMAL
+    // nothing for a number too large for it, as `Encode` has no way to fail
+    pub(crate) fn check_encoded(&self, value: &[u8]) -> Result<(), String> {
+        let is_date = matches!(
+            self.ty,
+            DataType::DateN | DataType::DateTime2N | DataType::DateTimeOffsetN
+        );
+
+        if is_date && value.is_empty() {
+            return Err(format!("date out of range for {}", self.name()));
+        }
+
+        if matches!(self.ty, DataType::DecimalN | DataType::NumericN) && value.is_empty() {
+            return Err(format!("number out of range for {}", self.name()));
+        }
+
+        Ok(())
     }
 
     pub(crate) fn put_fixed_value<'q, T: Encode<'q, Mssql>>(&self, buf: &mut Vec<u8>, value: T) {
         let _ = value.encode(buf);
     }
 
-    pub(crate) fn put_byte_len_value<'q, T: Encode<'q, Mssql>>(&self, buf: &mut Vec<u8>, value: T) {
+    pub(crate) fn put_byte_len_value<'q, T: Encode<'q, Mssql>>(
+        &self,
+        buf: &mut Vec<u8>,
+        value: T,
+    ) -> Result<(), String> {
         let offset = buf.len();
         buf.push(0);
 
         let size = if let IsNull::Yes = value.encode(buf) {
-            0xFF
+            match self.ty {
+                // the legacy character and binary types use 0xFF for NULL
+                DataType::Char | DataType::VarChar | DataType::Binary | DataType::VarBinary => 0xFF,
+
+                _ => 0,
+            }
         } else {
+            self.check_encoded(&buf[(offset + 1)..])?;
+
             (buf.len() - offset - 1) as u8
         };
 
         buf[offset] = size;
+
+        Ok(())
     }
 
     pub(crate) fn put_short_len_value<'q, T: Encode<'q, Mssql>>(
@@ -482,6 +736,40 @@ impl TypeInfo {
         buf[offset..(offset + 4)].copy_from_slice(&size.to_le_bytes());
     }
 
+    // writes the value as a single PLP (partially length-prefixed) chunk
+    // https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-tds/3f983fde-0509-485a-8c40-a9fa6679a828
+    pub(crate) fn put_plp_value<'q, T: Encode<'q, Mssql>>(&self, buf: &mut Vec<u8>, value: T) {
+        let offset = buf.len();
+        buf.extend(&0_u64.to_le_bytes()); // [total length]
+        buf.extend(&0_u32.to_le_bytes()); // [chunk length]
+
+        if let IsNull::Yes = value.encode(buf) {
+            buf.truncate(offset);
+            buf.extend(&PLP_NULL.to_le_bytes());
+            return;
+        }
+
+        let size = buf.len() - offset - 12;
+
+        buf[offset..(offset + 8)].copy_from_slice(&(size as u64).to_le_bytes());
+        buf[(offset + 8)..(offset + 12)].copy_from_slice(&(size as u32).to_le_bytes());
+
+        // for an empty value, the empty chunk already is the terminator
+        if size > 0 {
+            buf.extend(&0_u32.to_le_bytes()); // [terminator]
+        }
+    }
+
+    // writes the column metadata and rows of a table-valued parameter
+    // https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-tds/c264db71-c1ec-4fe8-b5ef-19d54b1e6566
+    pub(crate) fn put_table_value<'q, T: Encode<'q, Mssql>>(&self, buf: &mut Vec<u8>, value: T) {
+        if let IsNull::Yes = value.encode(buf) {
+            buf.extend(&0xFFFF_u16.to_le_bytes()); // [TVP_NULL_TOKEN]
+            buf.push(0); // [TVP_END_TOKEN]
+            buf.push(0); // [TVP_END_TOKEN]
+        }
+    }
+
     pub(crate) fn name(&self) -> &'static str {
         match self.ty {
             DataType::Null => "NULL",
@@ -491,35 +779,68 @@ impl TypeInfo {
             DataType::BigInt => "BIGINT",
             DataType::Real => "REAL",
             DataType::Float => "FLOAT",
+            DataType::Bit | DataType::BitN => "BIT",
 
+            // the variable-length types take the name of the fixed-length type of their size;
+            // a size that does not match one is rejected when the TYPE_INFO is read
             DataType::IntN => match self.size {
                 1 => "TINYINT",
                 2 => "SMALLINT",
                 4 => "INT",
                 8 => "BIGINT",
-
-                n => unreachable!("invalid size {} for int", n),
+                _ => "INTN",
             },
 
             DataType::FloatN => match self.size {
                 4 => "REAL",
                 8 => "FLOAT",
+                _ => "FLOATN",
+            },
 
-                n => unreachable!("invalid size {} for float", n),
+            DataType::MoneyN => match self.size {
+                4 => "SMALLMONEY",
+                8 => "MONEY",
+                _ => "MONEYN",
             },
 
-            DataType::VarChar => "VARCHAR",
+            DataType::DateTimeN => match self.size {
+                4 => "SMALLDATETIME",
+                _ => "DATETIME",
+            },
+
+            DataType::Decimal | DataType::DecimalN => "DECIMAL",
+            DataType::Numeric | DataType::NumericN => "NUMERIC",
+            DataType::Money => "MONEY",
+            DataType::SmallMoney => "SMALLMONEY",
+            DataType::Guid => "UNIQUEIDENTIFIER",
+
+            DataType::DateN => "DATE",
+            DataType::TimeN => "TIME",
+            DataType::DateTime2N => "DATETIME2",
+            DataType::DateTimeOffsetN => "DATETIMEOFFSET",
+            DataType::DateTime => "DATETIME",
+            DataType::SmallDateTime => "SMALLDATETIME",
+
+            DataType::VarChar | DataType::BigVarChar => "VARCHAR",
+            DataType::Char | DataType::BigChar => "CHAR",
             DataType::NVarChar => "NVARCHAR",
-            DataType::BigVarChar => "BIGVARCHAR",
-            DataType::Char => "CHAR",
-            DataType::BigChar => "BIGCHAR",
             DataType::NChar => "NCHAR",
+            DataType::Text => "TEXT",
+            DataType::NText => "NTEXT",
+
+            DataType::VarBinary | DataType::BigVarBinary => "VARBINARY",
+            DataType::Binary | DataType::BigBinary => "BINARY",
+            DataType::Image => "IMAGE",
 
-            _ => unimplemented!("name: unsupported data type {:?}", self.ty),
+            DataType::Xml => "XML",
+            DataType::Variant => "SQL_VARIANT",
+            DataType::UserDefined => "UDT",
+            DataType::Tvp => "TABLE",
         }
     }
 
-    pub(crate) fn fmt(&self, s: &mut String) {
+    // writes the type as used in a declaration, e.g. `datetime2(7)` or `varbinary(max)`
+    pub(crate) fn fmt(&self, s: &mut String) -> Result<(), Error> {
         match self.ty {
             DataType::Null => s.push_str("nvarchar(1)"),
             DataType::TinyInt => s.push_str("tinyint"),
@@ -528,7 +849,18 @@ impl TypeInfo {
             DataType::BigInt => s.push_str("bigint"),
             DataType::Real => s.push_str("real"),
             DataType::Float => s.push_str("float"),
-            DataType::Bit => s.push_str("bit"),
+            DataType::Bit | DataType::BitN => s.push_str("bit"),
+            DataType::Money => s.push_str("money"),
+            DataType::SmallMoney => s.push_str("smallmoney"),
+            DataType::Guid => s.push_str("uniqueidentifier"),
+            DataType::DateN => s.push_str("date"),
+            DataType::DateTime => s.push_str("datetime"),
+            DataType::SmallDateTime => s.push_str("smalldatetime"),
+            DataType::Text => s.push_str("text"),
+            DataType::NText => s.push_str("ntext"),
+            DataType::Image => s.push_str("image"),
+            DataType::Xml => s.push_str("xml"),
+            DataType::Variant => s.push_str("sql_variant"),
 
             DataType::IntN => s.push_str(match self.size {
                 1 => "tinyint",
@@ -536,51 +868,139 @@ impl TypeInfo {
                 4 => "int",
                 8 => "bigint",
 
-                n => unreachable!("invalid size {} for int", n),
+                n => return Err(err_protocol!("invalid size {} for int", n)),
             }),
 
             DataType::FloatN => s.push_str(match self.size {
                 4 => "real",
                 8 => "float",
 
-                n => unreachable!("invalid size {} for float", n),
+                n => return Err(err_protocol!("invalid size {} for float", n)),
+            }),
+
+            DataType::MoneyN => s.push_str(match self.size {
+                4 => "smallmoney",
+                8 => "money",
+
+                n => return Err(err_protocol!("invalid size {} for money", n)),
             }),
 
+            DataType::DateTimeN => s.push_str(match self.size {
+                4 => "smalldatetime",
+                8 => "datetime",
+
+                n => return Err(err_protocol!("invalid size {} for datetime", n)),
+            }),
+
+            DataType::TimeN | DataType::DateTime2N | DataType::DateTimeOffsetN => {
+                s.push_str(match self.ty {
+                    DataType::TimeN => "time",
+                    DataType::DateTime2N => "datetime2",
+                    _ => "datetimeoffset",
+                });
+
+                // fractional seconds scale
+                s.push('(');
+                s.push_str(itoa::Buffer::new().format(self.scale));
+                s.push(')');
+            }
+
+            DataType::Decimal | DataType::Numeric | DataType::DecimalN | DataType::NumericN => {
+                s.push_str(match self.ty {
+                    DataType::Decimal | DataType::DecimalN => "decimal",
+                    _ => "numeric",
+                });
+
+                // precision and scale
+                s.push('(');
+                s.push_str(itoa::Buffer::new().format(self.precision));
+                s.push(',');
+                s.push_str(itoa::Buffer::new().format(self.scale));
+                s.push(')');
+            }
+
             DataType::VarChar
-            | DataType::NVarChar
             | DataType::BigVarChar
             | DataType::Char
             | DataType::BigChar
-            | DataType::NChar => {
-                // name
+            | DataType::VarBinary
+            | DataType::BigVarBinary
+            | DataType::Binary
+            | DataType::BigBinary => {
                 s.push_str(match self.ty {
-                    DataType::VarChar => "varchar",
-                    DataType::NVarChar => "nvarchar",
-                    DataType::BigVarChar => "bigvarchar",
-                    DataType::Char => "char",
-                    DataType::BigChar => "bigchar",
-                    DataType::NChar => "nchar",
+                    DataType::VarChar | DataType::BigVarChar => "varchar",
+                    DataType::Char | DataType::BigChar => "char",
+                    DataType::VarBinary | DataType::BigVarBinary => "varbinary",
+                    _ => "binary",
+                });
 
-                    _ => unreachable!(),
+                self.fmt_length(s, self.size, 8000)?;
+            }
+
+            DataType::NVarChar | DataType::NChar => {
+                s.push_str(match self.ty {
+                    DataType::NVarChar => "nvarchar",
+                    _ => "nchar",
                 });
 
-                // size
-                if self.size < 8000 && self.size > 0 {
-                    s.push_str("(");
-                    s.push_str(itoa::Buffer::new().format(self.size));
-                    s.push_str(")");
-                } else {
-                    s.push_str("(max)");
-                }
+                // the size is in bytes but the length is in (UTF-16) characters
+                self.fmt_length(s, self.size.div_ceil(2), 4000)?;
             }
 
-            DataType::BitN => {
-                s.push_str("bit");
+            DataType::UserDefined => {
+                return Err(err_protocol!("fmt: unsupported data type {:?}", self.ty));
             }
 
-            _ => unimplemented!("fmt: unsupported data type {:?}", self.ty),
+            DataType::Tvp => match &self.type_name {
+                Some(type_name) => {
+                    s.push_str(type_name);
+                    s.push_str(" READONLY");
+                }
+
+                None => {
+                    return Err(err_protocol!(
+                        "fmt: table-valued parameter without a type name"
+                    ));
+                }
+            },
         }
+
+        Ok(())
     }
+
+    // writes the `(n)` or `(max)` length of a character or binary type
+    fn fmt_length(&self, s: &mut String, length: u32, max_length: u32) -> Result<(), Error> {
+        if length > 0 && length <= max_length {
+            s.push('(');
+            s.push_str(itoa::Buffer::new().format(length));
+            s.push(')');
+        } else if matches!(
+            self.ty,
+            DataType::VarChar
+                | DataType::BigVarChar
+                | DataType::NVarChar
+                | DataType::VarBinary
+                | DataType::BigVarBinary
+        ) {
+            s.push_str("(max)");
+        } else {
+            // there is no CHAR(MAX) or BINARY(MAX)
+            return Err(err_protocol!(
+                "invalid length {} for {}",
+                length,
+                self.name()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// strips the brackets from a quoted identifier, e.g. `[dbo]`
+fn unquote(name: &str) -> &str {
+    name.strip_prefix('[')
+        .and_then(|name| name.strip_suffix(']'))
+        .unwrap_or(name)
 }
 
 impl DataType {
@@ -623,6 +1043,7 @@ impl DataType {
             0xe7 => DataType::NVarChar,
             0xef => DataType::NChar,
             0xf1 => DataType::Xml,
+            0xf3 => DataType::Tvp,
             0xf0 => DataType::UserDefined,
             0x23 => DataType::Text,
             0x22 => DataType::Image,
@@ -671,3 +1092,108 @@ fn test_get() {
     let type_info = TypeInfo::get(&mut buf).unwrap();
     assert_eq!(type_info, TypeInfo::new(DataType::IntN, 4));
 }
+
+#[test]
+fn test_put_plp_value() {
+    let type_info = TypeInfo::new(DataType::BigVarBinary, 0xFFFF);
+
+    let mut buf = Vec::new();
+    type_info.put_value(&mut buf, &[1_u8, 2, 3][..]).unwrap();
+
+    #[rustfmt::skip]
+    assert_eq!(buf, [
+        3, 0, 0, 0, 0, 0, 0, 0, // total length
+        3, 0, 0, 0, 1, 2, 3, // chunk
+        0, 0, 0, 0, // terminator
+    ]);
+
+    let mut buf = Vec::new();
+    type_info
+        .put_value(&mut buf, Option::<&[u8]>::None)
+        .unwrap();
+
+    assert_eq!(buf, PLP_NULL.to_le_bytes());
+}
+
+#[test]
+fn test_get_plp_value() {
+    let type_info = TypeInfo::new(DataType::BigVarBinary, 0xFFFF);
+
+    #[rustfmt::skip]
+    let mut buf = Bytes::from_static(&[
+        0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // unknown length
+        2, 0, 0, 0, 1, 2, // chunk
+        3, 0, 0, 0, 3, 4, 5, // chunk
+        0, 0, 0, 0, // terminator
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // NULL
+    ]);
+
+    assert_eq!(type_info.get_value(&mut buf).unwrap(), &[1, 2, 3, 4, 5][..]);
+    assert_eq!(type_info.get_value(&mut buf), None);
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn test_fmt() {
+    fn fmt(type_info: TypeInfo) -> String {
+        let mut s = String::new();
+        type_info.fmt(&mut s).unwrap();
+        s
+    }
+
+    assert_eq!(fmt(TypeInfo::time(DataType::DateTime2N, 7)), "datetime2(7)");
+    assert_eq!(fmt(TypeInfo::new(DataType::MoneyN, 8)), "money");
+    assert_eq!(fmt(TypeInfo::new(DataType::NVarChar, 20)), "nvarchar(10)");
+    assert_eq!(
+        fmt(TypeInfo::new(DataType::NVarChar, 0xFFFF)),
+        "nvarchar(max)"
+    );
+    assert_eq!(
+        fmt(TypeInfo::new(DataType::BigVarBinary, 8000)),
+        "varbinary(8000)"
+    );
+    assert_eq!(
+        fmt(TypeInfo::new(DataType::BigVarBinary, 0xFFFF)),
+        "varbinary(max)"
+    );
+
+    let mut decimal = TypeInfo::new(DataType::DecimalN, 9);
+    decimal.precision = 18;
+    decimal.scale = 2;
+    assert_eq!(fmt(decimal), "decimal(18,2)");
+
+    assert!(TypeInfo::new(DataType::IntN, 3)
+        .fmt(&mut String::new())
+        .is_err());
+    assert!(TypeInfo::new(DataType::UserDefined, 0)
+        .fmt(&mut String::new())
+        .is_err());
+
+    assert_eq!(
+        TypeInfo::new(DataType::DateTimeN, 4).name(),
+        "SMALLDATETIME"
+    );
+    assert_eq!(TypeInfo::new(DataType::DateTimeN, 8).name(), "DATETIME");
+}
+
+#[test]
+fn test_from_system_type() {
+    assert_eq!(
+        TypeInfo::from_system_type(56, 4, 10, 0).unwrap(),
+        TypeInfo::new(DataType::IntN, 4)
+    );
+    assert_eq!(
+        TypeInfo::from_system_type(231, -1, 0, 0).unwrap(),
+        TypeInfo::new(DataType::NVarChar, 0xFFFF)
+    );
+    assert_eq!(
+        TypeInfo::from_system_type(42, 8, 27, 7).unwrap(),
+        TypeInfo::time(DataType::DateTime2N, 7)
+    );
+
+    let decimal = TypeInfo::from_system_type(106, 9, 18, 2).unwrap();
+    assert_eq!(decimal.ty, DataType::DecimalN);
+    assert_eq!((decimal.size, decimal.precision, decimal.scale), (9, 18, 2));
+
+    assert!(TypeInfo::from_system_type(0, 0, 0, 0).is_err());
+}
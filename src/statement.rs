@@ -18,6 +18,7 @@ pub struct MssqlStatement<'q> {
 pub(crate) struct MssqlStatementMetadata {
     pub(crate) columns: Vec<MssqlColumn>,
     pub(crate) column_names: HashMap<UStr, usize>,
+    pub(crate) parameters: Option<Either<Vec<MssqlTypeInfo>, usize>>,
 }
 
 impl<'q> Statement<'q> for MssqlStatement<'q> {
@@ -35,7 +36,11 @@ impl<'q> Statement<'q> for MssqlStatement<'q> {
     }
 
     fn parameters(&self) -> Option<Either<&[MssqlTypeInfo], usize>> {
-        None
+        match &self.metadata.parameters {
+            Some(Either::Left(types)) => Some(Either::Left(types)),
+            Some(Either::Right(count)) => Some(Either::Right(*count)),
+            None => None,
+        }
     }
 
     fn columns(&self) -> &[MssqlColumn] {
@@ -52,6 +57,6 @@ impl ColumnIndex<MssqlStatement<'_>> for &'_ str {
             .column_names
             .get(*self)
             .ok_or_else(|| Error::ColumnNotFound((*self).into()))
-            .map(|v| *v)
+            .copied()
     }
 }
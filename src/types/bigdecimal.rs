@@ -0,0 +1,139 @@
+use bigdecimal::{BigDecimal, ToPrimitive};
+use num_bigint::{BigInt, Sign};
+
+use crate::decode::Decode;
+use crate::encode::{Encode, IsNull};
+use crate::error::BoxDynError;
+use crate::protocol::type_info::TypeInfo;
+use crate::types::{money, numeric, MssqlMoney};
+use crate::{Mssql, MssqlTypeInfo, MssqlValueRef};
+use sqlx_core::types::Type;
+
+impl Type<Mssql> for BigDecimal {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::decimal(numeric::MAX_PRECISION, 0))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        numeric::compatible(ty) || money::is_money(ty)
+    }
+}
+
+// returns whether the value is negative, its magnitude and its scale, rounding away
+// fractional digits that do not fit in the largest DECIMAL
+//
+// fails if the integer part alone does not fit
+fn to_numeric(value: &BigDecimal) -> Result<(bool, u128, u8), String> {
+    let (_, scale) = value.as_bigint_and_exponent();
+    let mut value = value.with_scale(scale.max(0));
+
+    loop {
+        let (digits, scale) = value.as_bigint_and_exponent();
+        let precision = (value.digits() as i64).max(scale);
+
+        if precision <= numeric::MAX_PRECISION as i64 {
+            let magnitude = digits
+                .magnitude()
+                .to_u128()
+                .ok_or_else(|| format!("{} does not fit in a DECIMAL", value))?;
+
+            return Ok((digits.sign() == Sign::Minus, magnitude, scale as u8));
+        }
+
+        let excess = precision - numeric::MAX_PRECISION as i64;
+
+        if scale < excess {
+            return Err(format!("{} does not fit in a DECIMAL", value));
+        }
+
+        value = value.round(scale - excess);
+    }
+}
+
+impl Encode<'_, Mssql> for BigDecimal {
+    fn produces(&self) -> Option<MssqlTypeInfo> {
+        Some(match to_numeric(self) {
+            Ok((_, magnitude, scale)) => numeric::type_info(magnitude, scale),
+
+            // declared at the largest precision, though it fits in none
+            Err(_) => <Self as Type<Mssql>>::type_info(),
+        })
+    }
+
+    // a value that does not fit in a DECIMAL is not written at all, which binding it
+    // reports as an error; see [TypeInfo::check_encoded]
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        if let Ok((negative, magnitude, scale)) = to_numeric(self) {
+            numeric::encode(negative, magnitude, scale, buf);
+        }
+
+        IsNull::No
+    }
+}
+
+impl Decode<'_, Mssql> for BigDecimal {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        if money::is_money(&value.type_info) {
+            return Ok(MssqlMoney(money::decode(value.as_bytes()?)?).to_bigdecimal());
+        }
+
+        let (negative, magnitude) = numeric::decode(value.as_bytes()?)?;
+
+        let sign = if negative { Sign::Minus } else { Sign::Plus };
+        let digits = BigInt::from_biguint(sign, magnitude.into());
+
+        Ok(BigDecimal::new(digits, value.type_info.0.scale as i64))
+    }
+}
+
+#[test]
+fn test_encode_bigdecimal() {
+    let value: BigDecimal = "0.1234567890123456789012345678901234567890"
+        .parse()
+        .unwrap();
+
+    // rounded to fit 38 digits
+    let ty = value.produces().unwrap();
+    assert_eq!(ty.0.precision, 38);
+    assert_eq!(ty.0.scale, 38);
+    assert_eq!(ty.0.size, 17);
+
+    let value: BigDecimal = "-1e3".parse().unwrap();
+
+    let ty = value.produces().unwrap();
+    assert_eq!(ty.0.precision, 4);
+    assert_eq!(ty.0.scale, 0);
+
+    let mut buf = Vec::new();
+    let _ = value.encode_by_ref(&mut buf);
+    assert_eq!(buf, [0, 0xe8, 0x03, 0, 0]);
+}
+
+#[test]
+fn test_encode_bigdecimal_out_of_range() {
+    let value: BigDecimal = "1e40".parse().unwrap();
+    assert!(to_numeric(&value).is_err());
+
+    let mut args = crate::MssqlArguments::default();
+    args.add(value);
+
+    assert!(matches!(
+        args.check("SELECT @p1"),
+        Err(crate::error::Error::Configuration(_))
+    ));
+}
+
+#[test]
+fn test_decode_bigdecimal() {
+    let bytes = sqlx_core::bytes::Bytes::from_static(&[0, 0x15, 0xcd, 0x5b, 0x07]);
+
+    let value = MssqlValueRef {
+        type_info: MssqlTypeInfo(TypeInfo::decimal(9, 4)),
+        data: Some(&bytes),
+    };
+
+    assert_eq!(
+        <BigDecimal as Decode<Mssql>>::decode(value).unwrap(),
+        "-12345.6789".parse::<BigDecimal>().unwrap()
+    );
+}
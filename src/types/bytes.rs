@@ -0,0 +1,135 @@
+use bytes::Bytes;
+
+use crate::decode::Decode;
+use crate::encode::{Encode, IsNull};
+use crate::error::{BoxDynError, UnexpectedNullError};
+use crate::protocol::type_info::{DataType, TypeInfo};
+use crate::{Mssql, MssqlTypeInfo, MssqlValueRef};
+use sqlx_core::types::Type;
+use std::borrow::Cow;
+
+// the largest VARBINARY(n); anything longer is sent as VARBINARY(MAX)
+const MAX_SIZE: usize = 8000;
+
+impl Type<Mssql> for [u8] {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::new(DataType::BigVarBinary, 0))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        matches!(
+            ty.0.ty,
+            DataType::BigVarBinary
+                | DataType::BigBinary
+                | DataType::VarBinary
+                | DataType::Binary
+                | DataType::Image
+        )
+    }
+}
+
+impl Type<Mssql> for Vec<u8> {
+    fn type_info() -> MssqlTypeInfo {
+        <[u8] as Type<Mssql>>::type_info()
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        <[u8] as Type<Mssql>>::compatible(ty)
+    }
+}
+
+impl Type<Mssql> for Cow<'_, [u8]> {
+    fn type_info() -> MssqlTypeInfo {
+        <[u8] as Type<Mssql>>::type_info()
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        <[u8] as Type<Mssql>>::compatible(ty)
+    }
+}
+
+impl Type<Mssql> for Bytes {
+    fn type_info() -> MssqlTypeInfo {
+        <[u8] as Type<Mssql>>::type_info()
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        <[u8] as Type<Mssql>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, Mssql> for &'_ [u8] {
+    fn produces(&self) -> Option<MssqlTypeInfo> {
+        // an empty value needs to be encoded as `varbinary(1)`
+        let size = match self.len() {
+            len if len > MAX_SIZE => 0xFFFF,
+            len => len.max(1) as u32,
+        };
+
+        Some(MssqlTypeInfo(TypeInfo::new(DataType::BigVarBinary, size)))
+    }
+
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        buf.extend_from_slice(self);
+
+        IsNull::No
+    }
+}
+
+impl Encode<'_, Mssql> for Vec<u8> {
+    fn produces(&self) -> Option<MssqlTypeInfo> {
+        <&[u8] as Encode<Mssql>>::produces(&self.as_slice())
+    }
+
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        <&[u8] as Encode<Mssql>>::encode_by_ref(&self.as_slice(), buf)
+    }
+}
+
+impl Encode<'_, Mssql> for Cow<'_, [u8]> {
+    fn produces(&self) -> Option<MssqlTypeInfo> {
+        <&[u8] as Encode<Mssql>>::produces(&self.as_ref())
+    }
+
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        <&[u8] as Encode<Mssql>>::encode_by_ref(&self.as_ref(), buf)
+    }
+}
+
+impl Encode<'_, Mssql> for Bytes {
+    fn produces(&self) -> Option<MssqlTypeInfo> {
+        <&[u8] as Encode<Mssql>>::produces(&self.as_ref())
+    }
+
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        <&[u8] as Encode<Mssql>>::encode_by_ref(&self.as_ref(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Mssql> for &'r [u8] {
+    fn decode(value: MssqlValueRef<'r>) -> Result<Self, BoxDynError> {
+        value.as_bytes()
+    }
+}
+
+impl Decode<'_, Mssql> for Vec<u8> {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        Ok(value.as_bytes()?.to_vec())
+    }
+}
+
+impl<'r> Decode<'r, Mssql> for Cow<'r, [u8]> {
+    fn decode(value: MssqlValueRef<'r>) -> Result<Self, BoxDynError> {
+        Ok(Cow::Borrowed(value.as_bytes()?))
+    }
+}
+
+impl Decode<'_, Mssql> for Bytes {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        // a cheap clone of the row buffer
+        match value.data {
+            Some(data) => Ok(data.clone()),
+            None => Err(UnexpectedNullError.into()),
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use chrono::{
+    DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc,
+};
+
+use crate::decode::Decode;
+use crate::encode::{Encode, IsNull};
+use crate::error::BoxDynError;
+use crate::protocol::type_info::{DataType, TypeInfo};
+use crate::types::datetime::{self, Parts};
+use crate::{Mssql, MssqlTypeInfo, MssqlValueRef};
+use sqlx_core::types::Type;
+
+fn date_to_days(date: &NaiveDate) -> i64 {
+    // `num_days_from_ce` counts 0001-01-01 as day 1
+    date.num_days_from_ce() as i64 - 1
+}
+
+fn days_to_date(days: i64) -> Result<NaiveDate, BoxDynError> {
+    i32::try_from(days + 1)
+        .ok()
+        .and_then(NaiveDate::from_num_days_from_ce_opt)
+        .ok_or_else(|| format!("date out of range: {} days since 0001-01-01", days).into())
+}
+
+fn time_to_nanos(time: &NaiveTime) -> u64 {
+    // a leap second is folded into the second before it
+    time.num_seconds_from_midnight() as u64 * 1_000_000_000
+        + time.nanosecond().min(999_999_999) as u64
+}
+
+fn nanos_to_time(nanos: u64) -> Result<NaiveTime, BoxDynError> {
+    NaiveTime::from_num_seconds_from_midnight_opt(
+        (nanos / 1_000_000_000) as u32,
+        (nanos % 1_000_000_000) as u32,
+    )
+    .ok_or_else(|| format!("time out of range: {} nanoseconds since midnight", nanos).into())
+}
+
+fn datetime_to_parts(datetime: &NaiveDateTime, offset: i16) -> Parts {
+    Parts {
+        days: date_to_days(&datetime.date()),
+        nanos: time_to_nanos(&datetime.time()),
+        offset,
+    }
+}
+
+fn parts_to_datetime(parts: &Parts) -> Result<NaiveDateTime, BoxDynError> {
+    Ok(days_to_date(parts.days)?.and_time(nanos_to_time(parts.nanos)?))
+}
+
+impl Type<Mssql> for NaiveDate {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::new(DataType::DateN, 3))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        datetime::is_date(ty)
+    }
+}
+
+impl Encode<'_, Mssql> for NaiveDate {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        let parts = Parts {
+            days: date_to_days(self),
+            ..Parts::default()
+        };
+
+        datetime::encode(&Self::type_info().0, &parts, buf);
+
+        IsNull::No
+    }
+}
+
+impl Decode<'_, Mssql> for NaiveDate {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        let parts = datetime::decode(&value.type_info.0, value.as_bytes()?)?;
+
+        days_to_date(parts.days)
+    }
+}
+
+impl Type<Mssql> for NaiveTime {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::time(DataType::TimeN, datetime::SCALE))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        datetime::is_time(ty)
+    }
+}
+
+impl Encode<'_, Mssql> for NaiveTime {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        let parts = Parts {
+            nanos: time_to_nanos(self),
+            ..Parts::default()
+        };
+
+        datetime::encode(&Self::type_info().0, &parts, buf);
+
+        IsNull::No
+    }
+}
+
+impl Decode<'_, Mssql> for NaiveTime {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        let parts = datetime::decode(&value.type_info.0, value.as_bytes()?)?;
+
+        nanos_to_time(parts.nanos)
+    }
+}
+
+impl Type<Mssql> for NaiveDateTime {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::time(DataType::DateTime2N, datetime::SCALE))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        datetime::is_datetime(ty)
+    }
+}
+
+impl Encode<'_, Mssql> for NaiveDateTime {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        datetime::encode(&Self::type_info().0, &datetime_to_parts(self, 0), buf);
+
+        IsNull::No
+    }
+}
+
+impl Decode<'_, Mssql> for NaiveDateTime {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        let parts = datetime::decode(&value.type_info.0, value.as_bytes()?)?;
+
+        // the offset of a DATETIMEOFFSET is dropped, leaving the time in UTC
+        parts_to_datetime(&parts)
+    }
+}
+
+impl Type<Mssql> for DateTime<Utc> {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::time(DataType::DateTimeOffsetN, datetime::SCALE))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        // a date and time without an offset is taken to be in UTC
+        datetime::is_datetimeoffset(ty) || datetime::is_datetime(ty)
+    }
+}
+
+impl Encode<'_, Mssql> for DateTime<Utc> {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        let parts = datetime_to_parts(&self.naive_utc(), 0);
+
+        datetime::encode(&Self::type_info().0, &parts, buf);
+
+        IsNull::No
+    }
+}
+
+impl Decode<'_, Mssql> for DateTime<Utc> {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        let parts = datetime::decode(&value.type_info.0, value.as_bytes()?)?;
+
+        Ok(Utc.from_utc_datetime(&parts_to_datetime(&parts)?))
+    }
+}
+
+impl Type<Mssql> for DateTime<FixedOffset> {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::time(DataType::DateTimeOffsetN, datetime::SCALE))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        datetime::is_datetimeoffset(ty)
+    }
+}
+
+impl Encode<'_, Mssql> for DateTime<FixedOffset> {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        let offset = (self.offset().local_minus_utc() / 60) as i16;
+        let parts = datetime_to_parts(&self.naive_utc(), offset);
+
+        datetime::encode(&Self::type_info().0, &parts, buf);
+
+        IsNull::No
+    }
+}
+
+impl Decode<'_, Mssql> for DateTime<FixedOffset> {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        let parts = datetime::decode(&value.type_info.0, value.as_bytes()?)?;
+
+        let offset = FixedOffset::east_opt(parts.offset as i32 * 60)
+            .ok_or_else(|| format!("invalid offset of {} minutes", parts.offset))?;
+
+        Ok(offset.from_utc_datetime(&parts_to_datetime(&parts)?))
+    }
+}
+
+#[test]
+fn test_datetimeoffset() {
+    let value = DateTime::parse_from_rfc3339("2023-06-15T13:45:30.1234567+02:00").unwrap();
+
+    let mut buf = Vec::new();
+    let _ = value.encode_by_ref(&mut buf);
+
+    #[rustfmt::skip]
+    assert_eq!(buf, [
+        // 11:45:30.1234567 in 100ns increments
+        0x87, 0x3f, 0xb8, 0x8e, 0x62,
+        // 738685 days since 0001-01-01
+        0x7d, 0x45, 0x0b,
+        // +120 minutes
+        0x78, 0x00,
+    ]);
+
+    let bytes = sqlx_core::bytes::Bytes::from(buf);
+
+    let decoded = <DateTime<FixedOffset> as Decode<Mssql>>::decode(MssqlValueRef {
+        type_info: <DateTime<FixedOffset> as Type<Mssql>>::type_info(),
+        data: Some(&bytes),
+    })
+    .unwrap();
+
+    assert_eq!(decoded, value);
+    assert_eq!(decoded.offset(), value.offset());
+}
+
+#[test]
+fn test_date_out_of_range() {
+    use crate::MssqlArguments;
+
+    let mut args = MssqlArguments::default();
+    args.add(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap());
+    args.add(
+        NaiveDate::from_ymd_opt(1, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap(),
+    );
+    assert!(args.check("").is_ok());
+
+    // the day before 0001-01-01 would wrap around to a date far in the future
+    let mut args = MssqlArguments::default();
+    args.add(NaiveDate::from_ymd_opt(0, 12, 31).unwrap());
+    assert!(args.check("").is_err());
+
+    let mut args = MssqlArguments::default();
+    args.add(
+        NaiveDate::from_ymd_opt(10000, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap(),
+    );
+    assert!(args.check("").is_err());
+}
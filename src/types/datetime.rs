@@ -0,0 +1,168 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::error::BoxDynError;
+use crate::protocol::type_info::{DataType, TypeInfo};
+use crate::MssqlTypeInfo;
+
+// the number of days from 0001-01-01, the epoch of DATE, to 1900-01-01, the epoch of DATETIME
+const DATETIME_EPOCH_DAYS: i64 = 693_595;
+
+// the number of days from 0001-01-01 to 9999-12-31, the last day SQL Server can store
+const MAX_DAYS: i64 = 3_652_058;
+
+const NANOS_PER_MINUTE: u64 = 60_000_000_000;
+
+// the scale date/time values are sent with; 100ns, the most SQL Server can store
+pub(crate) const SCALE: u8 = 7;
+
+pub(crate) fn is_date(ty: &MssqlTypeInfo) -> bool {
+    matches!(ty.0.ty, DataType::DateN)
+}
+
+pub(crate) fn is_time(ty: &MssqlTypeInfo) -> bool {
+    matches!(ty.0.ty, DataType::TimeN)
+}
+
+// a date and time without an offset
+pub(crate) fn is_datetime(ty: &MssqlTypeInfo) -> bool {
+    matches!(
+        ty.0.ty,
+        DataType::DateTime2N | DataType::DateTime | DataType::SmallDateTime | DataType::DateTimeN
+    )
+}
+
+pub(crate) fn is_datetimeoffset(ty: &MssqlTypeInfo) -> bool {
+    matches!(ty.0.ty, DataType::DateTimeOffsetN)
+}
+
+// a date/time value broken down into the parts shared by every date/time type
+//
+// for a DATETIMEOFFSET, the date and time are in UTC
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Parts {
+    // days since 0001-01-01
+    pub(crate) days: i64,
+
+    // nanoseconds since midnight
+    pub(crate) nanos: u64,
+
+    // offset from UTC, in minutes
+    pub(crate) offset: i16,
+}
+
+// writes the parts as the given type, one of DATE, TIME, DATETIME2 or DATETIMEOFFSET
+// https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-tds/786f5b8a-f87d-4980-9070-b9b7274c681d
+//
+// a date before 0001-01-01 or after 9999-12-31 is not written at all, which binding it
+// reports as an error; see [TypeInfo::check_encoded]
+pub(crate) fn encode(ty: &TypeInfo, parts: &Parts, buf: &mut Vec<u8>) {
+    if ty.ty != DataType::TimeN && !(0..=MAX_DAYS).contains(&parts.days) {
+        return;
+    }
+
+    if ty.ty != DataType::DateN {
+        // the time is the number of 10^-scale second increments since midnight,
+        // in as many bytes as the scale requires
+        let size = TypeInfo::time(DataType::TimeN, ty.scale).size as usize;
+        let increments = parts.nanos / 10_u64.pow(9 - ty.scale as u32);
+
+        buf.extend(&increments.to_le_bytes()[..size]);
+    }
+
+    if ty.ty != DataType::TimeN {
+        buf.extend(&(parts.days as u32).to_le_bytes()[..3]);
+    }
+
+    if ty.ty == DataType::DateTimeOffsetN {
+        buf.extend(&parts.offset.to_le_bytes());
+    }
+}
+
+pub(crate) fn decode(ty: &TypeInfo, buf: &[u8]) -> Result<Parts, BoxDynError> {
+    if buf.len() != ty.size as usize {
+        return Err(format!("invalid {:?} value of {} bytes", ty.ty, buf.len()).into());
+    }
+
+    let parts = match ty.ty {
+        DataType::DateN => Parts {
+            days: LittleEndian::read_u24(buf) as i64,
+            ..Parts::default()
+        },
+
+        DataType::TimeN | DataType::DateTime2N | DataType::DateTimeOffsetN => {
+            let size = TypeInfo::time(DataType::TimeN, ty.scale).size as usize;
+            let increments = LittleEndian::read_uint(buf, size);
+
+            let mut parts = Parts {
+                nanos: increments * 10_u64.pow(9 - ty.scale as u32),
+                ..Parts::default()
+            };
+
+            if ty.ty != DataType::TimeN {
+                parts.days = LittleEndian::read_u24(&buf[size..]) as i64;
+            }
+
+            if ty.ty == DataType::DateTimeOffsetN {
+                parts.offset = LittleEndian::read_i16(&buf[(size + 3)..]);
+            }
+
+            parts
+        }
+
+        // days since 1900-01-01 and 1/300 second ticks since midnight
+        DataType::DateTime | DataType::DateTimeN if buf.len() == 8 => Parts {
+            days: DATETIME_EPOCH_DAYS + LittleEndian::read_i32(buf) as i64,
+            nanos: LittleEndian::read_u32(&buf[4..]) as u64 * 10_000_000 / 3,
+            offset: 0,
+        },
+
+        // days since 1900-01-01 and minutes since midnight
+        DataType::SmallDateTime | DataType::DateTimeN => Parts {
+            days: DATETIME_EPOCH_DAYS + LittleEndian::read_u16(buf) as i64,
+            nanos: LittleEndian::read_u16(&buf[2..]) as u64 * NANOS_PER_MINUTE,
+            offset: 0,
+        },
+
+        ty => {
+            return Err(format!("{:?} is not a date/time type", ty).into());
+        }
+    };
+
+    Ok(parts)
+}
+
+#[test]
+fn test_encode_decode() {
+    // 2023-06-15 13:45:30.1234567 +02:00, stored as 11:45:30.1234567 UTC
+    let parts = Parts {
+        days: 738_685,
+        nanos: 42_330_123_456_700,
+        offset: 120,
+    };
+
+    for scale in 0..=7 {
+        let ty = TypeInfo::time(DataType::DateTimeOffsetN, scale);
+
+        let mut buf = Vec::new();
+        encode(&ty, &parts, &mut buf);
+        assert_eq!(buf.len(), ty.size as usize);
+
+        let decoded = decode(&ty, &buf).unwrap();
+        assert_eq!(decoded.days, parts.days);
+        assert_eq!(decoded.offset, parts.offset);
+        assert_eq!(
+            decoded.nanos,
+            parts.nanos - parts.nanos % 10_u64.pow(9 - scale as u32)
+        );
+    }
+
+    // DATETIME 1900-01-02 00:00:01.000
+    let parts = decode(
+        &TypeInfo::new(DataType::DateTimeN, 8),
+        &[1, 0, 0, 0, 0x2c, 0x01, 0, 0],
+    )
+    .unwrap();
+
+    assert_eq!(parts.days, DATETIME_EPOCH_DAYS + 1);
+    assert_eq!(parts.nanos, 1_000_000_000);
+}
@@ -4,6 +4,7 @@ use crate::decode::Decode;
 use crate::encode::{Encode, IsNull};
 use crate::error::BoxDynError;
 use crate::protocol::type_info::{DataType, TypeInfo};
+use crate::types::money;
 use crate::{Mssql, MssqlTypeInfo, MssqlValueRef};
 use sqlx_core::types::Type;
 
@@ -85,7 +86,9 @@ impl Type<Mssql> for i64 {
     }
 
     fn compatible(ty: &MssqlTypeInfo) -> bool {
-        matches!(ty.0.ty, DataType::BigInt | DataType::IntN) && ty.0.size == 8
+        // a MONEY or SMALLMONEY decodes to its number of ten-thousandths
+        (matches!(ty.0.ty, DataType::BigInt | DataType::IntN) && ty.0.size == 8)
+            || money::is_money(ty)
     }
 }
 
@@ -99,6 +102,10 @@ impl Encode<'_, Mssql> for i64 {
 
 impl Decode<'_, Mssql> for i64 {
     fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        if money::is_money(&value.type_info) {
+            return money::decode(value.as_bytes()?);
+        }
+
         Ok(LittleEndian::read_i64(value.as_bytes()?))
     }
 }
@@ -2,11 +2,36 @@ use crate::encode::{Encode, IsNull};
 use crate::protocol::type_info::{DataType, TypeInfo};
 use crate::{Mssql, MssqlTypeInfo};
 
+pub use money::MssqlMoney;
+pub use table_value::{MssqlTableRow, MssqlTableValue};
+
 mod bool;
+mod bytes;
 mod float;
 mod int;
+mod money;
 mod str;
+mod table_value;
 mod uint;
+mod uuid;
+
+#[cfg(any(feature = "bigdecimal", feature = "rust_decimal"))]
+mod numeric;
+
+#[cfg(any(feature = "chrono", feature = "time"))]
+mod datetime;
+
+#[cfg(feature = "bigdecimal")]
+mod bigdecimal;
+
+#[cfg(feature = "chrono")]
+mod chrono;
+
+#[cfg(feature = "rust_decimal")]
+mod rust_decimal;
+
+#[cfg(feature = "time")]
+mod time;
 
 impl<'q, T: 'q + Encode<'q, Mssql>> Encode<'q, Mssql> for Option<T> {
     fn encode(self, buf: &mut Vec<u8>) -> IsNull {
@@ -0,0 +1,137 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::decode::Decode;
+use crate::encode::{Encode, IsNull};
+use crate::error::BoxDynError;
+use crate::protocol::type_info::{DataType, TypeInfo};
+use crate::{Mssql, MssqlTypeInfo, MssqlValueRef};
+use sqlx_core::types::Type;
+
+/// The MSSQL [`MONEY`] and [`SMALLMONEY`] types.
+///
+/// Both store an amount as an integer number of ten-thousandths (4 decimal places);
+/// a `SMALLMONEY` is decoded into the same representation.
+///
+/// Reading a `MONEY` column straight into a `Decimal` (with the `rust_decimal` feature) or a
+/// `BigDecimal` (with the `bigdecimal` feature) also works. To send a parameter as `MONEY`
+/// rather than `DECIMAL`, wrap it in this type.
+///
+/// [`MONEY`]: https://docs.microsoft.com/en-us/sql/t-sql/data-types/money-and-smallmoney-transact-sql
+/// [`SMALLMONEY`]: https://docs.microsoft.com/en-us/sql/t-sql/data-types/money-and-smallmoney-transact-sql
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct MssqlMoney(
+    /// The raw integer value, in ten-thousandths.
+    pub i64,
+);
+
+impl MssqlMoney {
+    /// The number of decimal places of a `MONEY` value.
+    pub const SCALE: u32 = 4;
+
+    /// Convert the money value into a [`Decimal`][rust_decimal::Decimal].
+    #[cfg(feature = "rust_decimal")]
+    pub fn to_decimal(self) -> rust_decimal::Decimal {
+        rust_decimal::Decimal::new(self.0, Self::SCALE)
+    }
+
+    /// Convert a [`Decimal`][rust_decimal::Decimal] into a money value, rounding to 4 decimal
+    /// places.
+    ///
+    /// Returns `None` if the value does not fit.
+    #[cfg(feature = "rust_decimal")]
+    pub fn from_decimal(decimal: rust_decimal::Decimal) -> Option<Self> {
+        use rust_decimal::prelude::ToPrimitive;
+
+        let mut decimal = decimal.round_dp(Self::SCALE);
+        decimal.rescale(Self::SCALE);
+
+        decimal.mantissa().to_i64().map(Self)
+    }
+
+    /// Convert the money value into a [`BigDecimal`][bigdecimal::BigDecimal].
+    #[cfg(feature = "bigdecimal")]
+    pub fn to_bigdecimal(self) -> bigdecimal::BigDecimal {
+        bigdecimal::BigDecimal::new(self.0.into(), Self::SCALE as i64)
+    }
+
+    /// Convert a [`BigDecimal`][bigdecimal::BigDecimal] into a money value, rounding to 4
+    /// decimal places.
+    ///
+    /// Returns `None` if the value does not fit.
+    #[cfg(feature = "bigdecimal")]
+    pub fn from_bigdecimal(decimal: &bigdecimal::BigDecimal) -> Option<Self> {
+        use bigdecimal::ToPrimitive;
+
+        let (digits, _) = decimal
+            .round(Self::SCALE as i64)
+            .with_scale(Self::SCALE as i64)
+            .into_bigint_and_exponent();
+
+        digits.to_i64().map(Self)
+    }
+}
+
+pub(crate) fn is_money(ty: &MssqlTypeInfo) -> bool {
+    matches!(
+        ty.0.ty,
+        DataType::Money | DataType::SmallMoney | DataType::MoneyN
+    )
+}
+
+// reads a MONEY or SMALLMONEY as its number of ten-thousandths
+// https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-tds/1266679d-cd6e-492a-b2b2-3a9ba004196d
+pub(crate) fn decode(buf: &[u8]) -> Result<i64, BoxDynError> {
+    match buf.len() {
+        // a MONEY is sent as its high 4 bytes followed by its low 4 bytes
+        8 => {
+            let high = LittleEndian::read_i32(buf) as i64;
+            let low = LittleEndian::read_u32(&buf[4..]) as i64;
+
+            Ok((high << 32) | low)
+        }
+
+        4 => Ok(LittleEndian::read_i32(buf) as i64),
+
+        len => Err(format!("invalid MONEY value of {} bytes", len).into()),
+    }
+}
+
+impl Type<Mssql> for MssqlMoney {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::new(DataType::MoneyN, 8))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        is_money(ty)
+    }
+}
+
+impl Encode<'_, Mssql> for MssqlMoney {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        buf.extend(&((self.0 >> 32) as i32).to_le_bytes());
+        buf.extend(&(self.0 as u32).to_le_bytes());
+
+        IsNull::No
+    }
+}
+
+impl Decode<'_, Mssql> for MssqlMoney {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        decode(value.as_bytes()?).map(Self)
+    }
+}
+
+#[test]
+fn test_money() {
+    // -123456789.1234
+    let money = MssqlMoney(-1_234_567_891_234);
+
+    let mut buf = Vec::new();
+    let _ = money.encode_by_ref(&mut buf);
+    assert_eq!(buf, [0xe0, 0xfe, 0xff, 0xff, 0xde, 0xf6, 0x04, 0x8e]);
+
+    assert_eq!(decode(&buf).unwrap(), money.0);
+
+    // SMALLMONEY -1.5
+    assert_eq!(decode(&[0x68, 0xc5, 0xff, 0xff]).unwrap(), -15_000);
+}
@@ -0,0 +1,46 @@
+use crate::error::BoxDynError;
+use crate::protocol::type_info::{DataType, TypeInfo};
+use crate::MssqlTypeInfo;
+
+// the largest precision of a DECIMAL or NUMERIC
+pub(crate) const MAX_PRECISION: u8 = 38;
+
+pub(crate) fn compatible(ty: &MssqlTypeInfo) -> bool {
+    matches!(
+        ty.0.ty,
+        DataType::Decimal | DataType::Numeric | DataType::DecimalN | DataType::NumericN
+    )
+}
+
+// the number of decimal digits in the magnitude, at least 1
+pub(crate) fn digits(magnitude: u128) -> u8 {
+    magnitude.checked_ilog10().map_or(1, |d| d as u8 + 1)
+}
+
+// the TYPE_INFO for a value with the given magnitude and scale
+pub(crate) fn type_info(magnitude: u128, scale: u8) -> MssqlTypeInfo {
+    MssqlTypeInfo(TypeInfo::decimal(digits(magnitude).max(scale), scale))
+}
+
+// a DECIMAL value is a sign byte (1 for positive, 0 for negative) followed by the
+// magnitude as an unsigned little-endian integer sized to the precision
+// https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-tds/5e02042c-a7c9-4b6e-9d8e-f9be7f1bd2a5
+pub(crate) fn encode(negative: bool, magnitude: u128, scale: u8, buf: &mut Vec<u8>) {
+    let size = type_info(magnitude, scale).0.size as usize;
+
+    buf.push(if negative { 0 } else { 1 });
+    buf.extend(&magnitude.to_le_bytes()[..size - 1]);
+}
+
+// returns whether the value is negative and its magnitude
+pub(crate) fn decode(buf: &[u8]) -> Result<(bool, u128), BoxDynError> {
+    let (sign, magnitude) = match buf.split_first() {
+        Some((sign, magnitude)) if magnitude.len() <= 16 => (*sign, magnitude),
+        _ => return Err(format!("invalid DECIMAL value of {} bytes", buf.len()).into()),
+    };
+
+    let mut bytes = [0; 16];
+    bytes[..magnitude.len()].copy_from_slice(magnitude);
+
+    Ok((sign == 0, u128::from_le_bytes(bytes)))
+}
@@ -0,0 +1,123 @@
+use rust_decimal::Decimal;
+
+use crate::decode::Decode;
+use crate::encode::{Encode, IsNull};
+use crate::error::BoxDynError;
+use crate::protocol::type_info::TypeInfo;
+use crate::types::MssqlMoney;
+use crate::types::{money, numeric};
+use crate::{Mssql, MssqlTypeInfo, MssqlValueRef};
+use sqlx_core::types::Type;
+
+impl Type<Mssql> for Decimal {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::decimal(numeric::MAX_PRECISION, 0))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        numeric::compatible(ty) || money::is_money(ty)
+    }
+}
+
+impl Encode<'_, Mssql> for Decimal {
+    fn produces(&self) -> Option<MssqlTypeInfo> {
+        Some(numeric::type_info(
+            self.mantissa().unsigned_abs(),
+            self.scale() as u8,
+        ))
+    }
+
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        numeric::encode(
+            self.is_sign_negative(),
+            self.mantissa().unsigned_abs(),
+            self.scale() as u8,
+            buf,
+        );
+
+        IsNull::No
+    }
+}
+
+impl Decode<'_, Mssql> for Decimal {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        if money::is_money(&value.type_info) {
+            return Ok(MssqlMoney(money::decode(value.as_bytes()?)?).to_decimal());
+        }
+
+        let (negative, magnitude) = numeric::decode(value.as_bytes()?)?;
+
+        // the magnitude is at most 10^38, which fits an i128
+        let mantissa = if negative {
+            -(magnitude as i128)
+        } else {
+            magnitude as i128
+        };
+
+        Ok(Decimal::try_from_i128_with_scale(
+            mantissa,
+            value.type_info.0.scale as u32,
+        )?)
+    }
+}
+
+#[test]
+fn test_encode_decimal() {
+    let value: Decimal = "-12345.6789".parse().unwrap();
+
+    let ty = value.produces().unwrap();
+    assert_eq!(ty.0.precision, 9);
+    assert_eq!(ty.0.scale, 4);
+    assert_eq!(ty.0.size, 5);
+
+    let mut buf = Vec::new();
+    let _ = value.encode_by_ref(&mut buf);
+    assert_eq!(buf, [0, 0x15, 0xcd, 0x5b, 0x07]);
+}
+
+#[test]
+fn test_decode_decimal() {
+    let bytes = sqlx_core::bytes::Bytes::from_static(&[1, 0x15, 0xcd, 0x5b, 0x07, 0, 0, 0, 0]);
+
+    let value = MssqlValueRef {
+        type_info: MssqlTypeInfo(TypeInfo::decimal(19, 4)),
+        data: Some(&bytes),
+    };
+
+    assert_eq!(
+        <Decimal as Decode<Mssql>>::decode(value).unwrap(),
+        "12345.6789".parse::<Decimal>().unwrap()
+    );
+}
+
+#[test]
+fn test_table_value_decimal_out_of_range() {
+    use crate::types::MssqlTableValue;
+
+    // 29 digits before the decimal point leave room for a scale of at most 9
+    let large = Decimal::from_i128_with_scale(10_i128.pow(28), 0);
+
+    let mut table = MssqlTableValue::new("dbo.Amounts");
+    table.add_row((large,));
+
+    let err = table
+        .try_add_row((Decimal::from_i128_with_scale(1, 10),))
+        .unwrap_err();
+
+    assert!(matches!(err, crate::error::Error::Configuration(_)));
+    assert_eq!(table.len(), 1);
+
+    table.add_row((Decimal::from_i128_with_scale(1, 9),));
+    assert_eq!(table.len(), 2);
+
+    assert!(MssqlTableValue::try_from_rows(
+        "dbo.Amounts",
+        [(large,), (Decimal::from_i128_with_scale(1, 10),)]
+    )
+    .is_err());
+
+    let type_info = table.produces().unwrap();
+
+    let mut buf = Vec::new();
+    type_info.0.put_value(&mut buf, &table).unwrap();
+}
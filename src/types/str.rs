@@ -21,6 +21,7 @@ impl Type<Mssql> for str {
                 | DataType::VarChar
                 | DataType::BigChar
                 | DataType::Char
+                | DataType::Xml
         )
     }
 }
@@ -37,10 +38,16 @@ impl Type<Mssql> for String {
 
 impl Encode<'_, Mssql> for &'_ str {
     fn produces(&self) -> Option<MssqlTypeInfo> {
-        // an empty string needs to be encoded as `nvarchar(2)`
+        // an empty string needs to be encoded as `nvarchar(1)`, and anything over
+        // 8000 bytes as `nvarchar(max)`
+        let size = match self.len() * 2 {
+            size if size > 8000 => 0xFFFF,
+            size => (size as u32).max(2),
+        };
+
         Some(MssqlTypeInfo(TypeInfo {
             ty: DataType::NVarChar,
-            size: ((self.len() * 2) as u32).max(2),
+            size,
             scale: 0,
             precision: 0,
             collation: Some(Collation {
@@ -51,6 +58,7 @@ impl Encode<'_, Mssql> for &'_ str {
                 sort: 52,
                 version: 0,
             }),
+            type_name: None,
         }))
     }
 
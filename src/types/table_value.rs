@@ -0,0 +1,549 @@
+use std::ops::Range;
+
+use crate::encode::{Encode, IsNull};
+use crate::error::Error;
+use crate::protocol::col_meta_data::Flags;
+use crate::protocol::type_info::{DataType, TypeInfo};
+use crate::{Mssql, MssqlTypeInfo};
+use sqlx_core::types::Type;
+
+/// A [table-valued parameter], a whole table of rows bound as a single parameter.
+///
+/// The table is an instance of a user-defined table type, such as one created with
+/// `CREATE TYPE dbo.IdList AS TABLE (id INT)`. Rows are added as tuples whose values
+/// line up with the columns of that type.
+///
+/// In a query, the parameter is declared as `dbo.IdList READONLY` and can be used like
+/// any other table. It can also be bound to a parameter of a stored procedure
+/// called with [`MssqlProcedureCall`][crate::MssqlProcedureCall].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use welds_sqlx_mssql::{MssqlArguments, MssqlConnection};
+/// # use welds_sqlx_mssql::types::MssqlTableValue;
+/// # async fn example(conn: &mut MssqlConnection) -> Result<(), sqlx_core::Error> {
+/// let ids = MssqlTableValue::from_rows("dbo.IdList", [(1_i32,), (2,), (3,)]);
+///
+/// let mut args = MssqlArguments::default();
+/// args.add_named("@ids", ids);
+///
+/// let rows = sqlx_core::query::query_with(
+///     "SELECT * FROM orders WHERE id IN (SELECT id FROM @ids)",
+///     args,
+/// )
+/// .fetch_all(conn)
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [table-valued parameter]: https://docs.microsoft.com/en-us/sql/relational-databases/tables/use-table-valued-parameters-database-engine
+#[derive(Debug, Clone)]
+pub struct MssqlTableValue {
+    type_name: String,
+    columns: Vec<Column>,
+    values: Vec<Value>,
+    // the encoded values of all rows, one after the other
+    data: Vec<u8>,
+    rows: usize,
+    // set once the column types have been sent ahead of the rows, as by a bulk insert;
+    // the types of later values can then no longer change them
+    frozen: bool,
+    // why the row being added cannot be, if it cannot
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Column {
+    type_info: MssqlTypeInfo,
+    // whether any value of this column was not NULL; until then, the type is only
+    // the one of the Rust type and gives way to the first value that has one
+    has_value: bool,
+    // for a DECIMAL, the most digits before the decimal point of any of its values
+    integer_digits: u8,
+}
+
+#[derive(Debug, Clone)]
+struct Value {
+    // the scale the value was encoded with, for a DECIMAL
+    scale: u8,
+    data: Option<Range<usize>>,
+}
+
+impl MssqlTableValue {
+    /// Creates an empty table of the given (optionally schema-qualified) table type.
+    pub fn new(type_name: impl Into<String>) -> Self {
+        Self {
+            type_name: type_name.into(),
+            columns: Vec::new(),
+            values: Vec::new(),
+            data: Vec::new(),
+            rows: 0,
+            frozen: false,
+            error: None,
+        }
+    }
+
+    /// Creates a table of the given table type holding the given rows.
+    ///
+    /// Fails on the first row that [`try_add_row`][Self::try_add_row] fails on.
+    pub fn try_from_rows<R>(
+        type_name: impl Into<String>,
+        rows: impl IntoIterator<Item = R>,
+    ) -> Result<Self, Error>
+    where
+        R: MssqlTableRow,
+    {
+        let mut table = Self::new(type_name);
+
+        for row in rows {
+            table.try_add_row(row)?;
+        }
+
+        Ok(table)
+    }
+
+    /// Creates a table of the given table type holding the given rows.
+    ///
+    /// # Panics
+    ///
+    /// On the first row that [`try_add_row`][Self::try_add_row] fails on; see
+    /// [`try_from_rows`][Self::try_from_rows] to handle that as an error instead.
+    pub fn from_rows<R>(type_name: impl Into<String>, rows: impl IntoIterator<Item = R>) -> Self
+    where
+        R: MssqlTableRow,
+    {
+        let mut table = Self::new(type_name);
+
+        for row in rows {
+            table.add_row(row);
+        }
+
+        table
+    }
+
+    /// Adds a row to the table.
+    ///
+    /// # Panics
+    ///
+    /// If [`try_add_row`][Self::try_add_row] fails.
+    pub fn add_row(&mut self, row: impl MssqlTableRow) -> &mut Self {
+        if let Err(error) = self.try_add_row(row) {
+            panic!("{}", error);
+        }
+
+        self
+    }
+
+    /// The number of rows in the table.
+    pub fn len(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns `true` if the table has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+
+    /// Adds a row to the table.
+    ///
+    /// The column types are taken from the values. Variable-length types are sent at their
+    /// maximum length, e.g. `nvarchar(max)`, and a `DECIMAL` column takes the largest scale
+    /// of its values.
+    ///
+    /// Fails, leaving the table as it was, if the row has a different number of values than
+    /// the rows before it, a value has a different type than the values before it in the
+    /// same column, a value cannot be stored by SQL Server (such as a date before the year 1),
+    /// or a `DECIMAL` value does not fit its column once scaled to the largest scale of the
+    /// column, that is the digits before its decimal point and the scale add up to more
+    /// than 38.
+    pub fn try_add_row(&mut self, row: impl MssqlTableRow) -> Result<&mut Self, Error> {
+        let start = (self.values.len(), self.data.len());
+
+        // the types of the columns follow the values until the table is frozen
+        let columns = (!self.frozen).then(|| self.columns.clone());
+
+        row.add_to(self);
+
+        let len = self.values.len() - start.0;
+
+        if len != self.columns.len() && self.error.is_none() {
+            self.error = Some(format!(
+                "a row of {} values was added to a table of {} columns",
+                len,
+                self.columns.len()
+            ));
+        }
+
+        if let Some(error) = self.error.take() {
+            self.values.truncate(start.0);
+            self.data.truncate(start.1);
+
+            if let Some(columns) = columns {
+                self.columns = columns;
+            }
+
+            return Err(Error::Configuration(error.into()));
+        }
+
+        self.rows += 1;
+
+        Ok(self)
+    }
+
+    pub(crate) fn column_types(&self) -> impl ExactSizeIterator<Item = &MssqlTypeInfo> {
+        self.columns.iter().map(|column| &column.type_info)
+    }
+
+    // keeps the types of the columns from changing with the rows added from now on
+    pub(crate) fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    // removes the rows, keeping the columns
+    pub(crate) fn clear(&mut self) {
+        self.values.clear();
+        self.data.clear();
+        self.rows = 0;
+    }
+
+    fn add<'q, T>(&mut self, column: usize, value: T)
+    where
+        T: Encode<'q, Mssql> + Type<Mssql>,
+    {
+        // a NULL produces the NULL type, which has nothing to say about the column
+        let mut type_info = value
+            .produces()
+            .filter(|ty| !ty.0.is_null())
+            .unwrap_or_else(T::type_info);
+
+        let offset = self.data.len();
+        let is_null = matches!(value.encode(&mut self.data), IsNull::Yes);
+
+        let data = if is_null {
+            self.data.truncate(offset);
+            None
+        } else {
+            Some(offset..self.data.len())
+        };
+
+        if data.is_some() {
+            if let Err(error) = type_info.0.check_encoded(&self.data[offset..]) {
+                self.error.get_or_insert(error);
+            }
+        }
+
+        let integer_digits = match (&data, type_info.0.ty) {
+            (Some(range), DataType::DecimalN | DataType::NumericN) => {
+                integer_digits(&self.data[range.clone()], type_info.0.scale)
+            }
+
+            _ => 0,
+        };
+
+        match type_info.0.ty {
+            // sent at their maximum length so values of any length fit
+            DataType::NVarChar | DataType::BigVarChar | DataType::BigVarBinary => {
+                type_info.0.size = 0xFFFF;
+            }
+
+            // sent at the largest precision; see [put_decimal]
+            DataType::DecimalN | DataType::NumericN => {
+                type_info.0 = TypeInfo::decimal(38, type_info.0.scale);
+            }
+
+            _ => {}
+        }
+
+        self.values.push(Value {
+            scale: type_info.0.scale,
+            data,
+        });
+
+        if self.rows == 0 && !self.frozen && column == self.columns.len() {
+            self.columns.push(Column {
+                type_info,
+                has_value: !is_null,
+                integer_digits,
+            });
+
+            return;
+        }
+
+        let Some(col) = self.columns.get_mut(column) else {
+            // the row is too long; reported once it is complete
+            return;
+        };
+
+        if is_null {
+            return;
+        }
+
+        let is_decimal = matches!(col.type_info.0.ty, DataType::DecimalN)
+            && matches!(type_info.0.ty, DataType::DecimalN);
+
+        // the scale of the column once this value is in it
+        let scale = if self.frozen {
+            col.type_info.0.scale
+        } else {
+            col.type_info.0.scale.max(type_info.0.scale)
+        };
+
+        if !col.has_value && !self.frozen {
+            col.type_info = type_info;
+            col.has_value = true;
+            col.integer_digits = integer_digits;
+        } else if is_decimal && type_info.0.scale <= scale {
+            // scaled up to the column when encoded, which must not take any of its values
+            // past the largest precision
+            if col.integer_digits.max(integer_digits) + scale > 38 {
+                self.error.get_or_insert_with(|| {
+                    format!(
+                        "column {} was given a DECIMAL value that does not fit in DECIMAL(38, {})",
+                        column, scale
+                    )
+                });
+            } else {
+                col.type_info.0.scale = scale;
+                col.integer_digits = col.integer_digits.max(integer_digits);
+            }
+        } else if col.type_info != type_info && self.error.is_none() {
+            self.error = Some(format!(
+                "column {} was given a value of type {} after values of type {}",
+                column, type_info, col.type_info
+            ));
+        }
+    }
+
+    // writes each row as the given token followed by its values
+    pub(crate) fn put_rows(&self, buf: &mut Vec<u8>, token: u8) {
+        if self.columns.is_empty() {
+            return;
+        }
+
+        for row in self.values.chunks(self.columns.len()) {
+            buf.push(token);
+
+            for (value, column) in row.iter().zip(&self.columns) {
+                let type_info = &column.type_info.0;
+                let data = value.data.clone().map(|range| &self.data[range]);
+
+                match data {
+                    // each value was checked as it was added
+                    Some(data) if type_info.ty == DataType::DecimalN => {
+                        let _ = Self::put_decimal(buf, data, value.scale, type_info.scale);
+                    }
+
+                    _ => {
+                        let _ = type_info.put_value(buf, data);
+                    }
+                }
+            }
+        }
+    }
+
+    // a DECIMAL is sent at the largest precision, with the scale of its column
+    fn put_decimal(
+        buf: &mut Vec<u8>,
+        value: &[u8],
+        from_scale: u8,
+        to_scale: u8,
+    ) -> Result<(), String> {
+        let (sign, magnitude) = decimal(value).ok_or("invalid DECIMAL value")?;
+
+        let magnitude = 10_u128
+            .checked_pow(to_scale.saturating_sub(from_scale) as u32)
+            .and_then(|factor| magnitude.checked_mul(factor))
+            .filter(|magnitude| magnitude.checked_ilog10().unwrap_or(0) < 38)
+            .ok_or_else(|| format!("DECIMAL value does not fit in DECIMAL(38, {})", to_scale))?;
+
+        buf.push(17);
+        buf.push(sign);
+        buf.extend(&magnitude.to_le_bytes());
+
+        Ok(())
+    }
+}
+
+impl Type<Mssql> for MssqlTableValue {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::new(DataType::Tvp, 0))
+    }
+}
+
+impl Encode<'_, Mssql> for MssqlTableValue {
+    fn produces(&self) -> Option<MssqlTypeInfo> {
+        let mut type_info = TypeInfo::new(DataType::Tvp, 0);
+        type_info.type_name = Some(self.type_name.clone());
+
+        Some(MssqlTypeInfo(type_info))
+    }
+
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        // a table without columns is sent as TVP_NULL_TOKEN, which is how an empty table
+        // goes to the server; see [TypeInfo::put_table_value]
+        if self.columns.is_empty() {
+            return IsNull::Yes;
+        }
+
+        // [TVP_COLMETADATA]
+        buf.extend(&(self.columns.len() as u16).to_le_bytes()); // [Count]
+
+        for column in &self.columns {
+            buf.extend(&0_u32.to_le_bytes()); // [UserType]
+            buf.extend(&Flags::NULLABLE.bits().to_le_bytes()); // [Flags]
+            column.type_info.0.put(buf); // [TYPE_INFO]
+            buf.push(0); // [ColName] is always empty
+        }
+
+        buf.push(0); // [TVP_END_TOKEN]
+
+        self.put_rows(buf, 1); // [TVP_ROW_TOKEN]
+
+        buf.push(0); // [TVP_END_TOKEN]
+
+        IsNull::No
+    }
+}
+
+// the sign byte and the magnitude of an encoded DECIMAL value
+fn decimal(value: &[u8]) -> Option<(u8, u128)> {
+    let (sign, magnitude) = value.split_first().filter(|(_, m)| m.len() <= 16)?;
+
+    let mut bytes = [0; 16];
+    bytes[..magnitude.len()].copy_from_slice(magnitude);
+
+    Some((*sign, u128::from_le_bytes(bytes)))
+}
+
+// the number of digits before the decimal point of an encoded DECIMAL value
+fn integer_digits(value: &[u8], scale: u8) -> u8 {
+    match decimal(value).and_then(|(_, magnitude)| magnitude.checked_ilog10()) {
+        Some(digits) => (digits as u8 + 1).saturating_sub(scale),
+        None => 0,
+    }
+}
+
+/// A row of a [`MssqlTableValue`] or of a [`MssqlBulkInsert`][crate::MssqlBulkInsert].
+///
+/// Implemented for tuples of up to 16 values.
+pub trait MssqlTableRow {
+    #[doc(hidden)]
+    fn add_to(self, table: &mut MssqlTableValue);
+}
+
+macro_rules! impl_table_row {
+    ($($T:ident: $idx:tt),+) => {
+        impl<'q, $($T),+> MssqlTableRow for ($($T,)+)
+        where
+            $($T: 'q + Encode<'q, Mssql> + Type<Mssql>,)+
+        {
+            fn add_to(self, table: &mut MssqlTableValue) {
+                $(table.add($idx, self.$idx);)+
+            }
+        }
+    };
+}
+
+impl_table_row!(T1: 0);
+impl_table_row!(T1: 0, T2: 1);
+impl_table_row!(T1: 0, T2: 1, T3: 2);
+impl_table_row!(T1: 0, T2: 1, T3: 2, T4: 3);
+impl_table_row!(T1: 0, T2: 1, T3: 2, T4: 3, T5: 4);
+impl_table_row!(T1: 0, T2: 1, T3: 2, T4: 3, T5: 4, T6: 5);
+impl_table_row!(T1: 0, T2: 1, T3: 2, T4: 3, T5: 4, T6: 5, T7: 6);
+impl_table_row!(T1: 0, T2: 1, T3: 2, T4: 3, T5: 4, T6: 5, T7: 6, T8: 7);
+impl_table_row!(T1: 0, T2: 1, T3: 2, T4: 3, T5: 4, T6: 5, T7: 6, T8: 7, T9: 8);
+impl_table_row!(T1: 0, T2: 1, T3: 2, T4: 3, T5: 4, T6: 5, T7: 6, T8: 7, T9: 8, T10: 9);
+impl_table_row!(T1: 0, T2: 1, T3: 2, T4: 3, T5: 4, T6: 5, T7: 6, T8: 7, T9: 8, T10: 9, T11: 10);
+impl_table_row!(
+    T1: 0, T2: 1, T3: 2, T4: 3, T5: 4, T6: 5, T7: 6, T8: 7, T9: 8, T10: 9, T11: 10, T12: 11
+);
+impl_table_row!(
+    T1: 0, T2: 1, T3: 2, T4: 3, T5: 4, T6: 5, T7: 6, T8: 7, T9: 8, T10: 9, T11: 10, T12: 11,
+    T13: 12
+);
+impl_table_row!(
+    T1: 0, T2: 1, T3: 2, T4: 3, T5: 4, T6: 5, T7: 6, T8: 7, T9: 8, T10: 9, T11: 10, T12: 11,
+    T13: 12, T14: 13
+);
+impl_table_row!(
+    T1: 0, T2: 1, T3: 2, T4: 3, T5: 4, T6: 5, T7: 6, T8: 7, T9: 8, T10: 9, T11: 10, T12: 11,
+    T13: 12, T14: 13, T15: 14
+);
+impl_table_row!(
+    T1: 0, T2: 1, T3: 2, T4: 3, T5: 4, T6: 5, T7: 6, T8: 7, T9: 8, T10: 9, T11: 10, T12: 11,
+    T13: 12, T14: 13, T15: 14, T16: 15
+);
+
+#[test]
+fn test_encode_table_value() {
+    let mut table = MssqlTableValue::new("[dbo].[IdList]");
+    table.add_row((1_i32, None::<&str>));
+    table.add_row((2_i32, Some("a")));
+
+    let mut args = crate::MssqlArguments::default();
+    args.add_named("ids", table);
+
+    assert_eq!(args.declarations, "@ids [dbo].[IdList] READONLY");
+
+    let type_info = Encode::<Mssql>::produces(&MssqlTableValue::new("dbo.IdList")).unwrap();
+
+    let mut buf = Vec::new();
+    type_info.0.put(&mut buf);
+
+    #[rustfmt::skip]
+    assert_eq!(buf, [
+        0xf3, // TVP
+        0, // database
+        3, b'd', 0, b'b', 0, b'o', 0, // schema
+        6, b'I', 0, b'd', 0, b'L', 0, b'i', 0, b's', 0, b't', 0, // name
+    ]);
+
+    let table = MssqlTableValue::from_rows("dbo.IdList", [(1_i32, None::<&str>), (2, Some("a"))]);
+
+    let mut buf = Vec::new();
+    type_info.0.put_value(&mut buf, &table).unwrap();
+
+    #[rustfmt::skip]
+    assert_eq!(buf, [
+        2, 0, // columns
+        0, 0, 0, 0, 1, 0, 0x26, 4, 0, // int
+        0, 0, 0, 0, 1, 0, 0xe7, 0xff, 0xff, 0x09, 0x04, 0xd0, 0x00, 0x34, 0, // nvarchar(max)
+        0, // end of metadata
+        1, 4, 1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // (1, NULL)
+        1, 4, 2, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, b'a', 0, 0, 0, 0, 0, // (2, 'a')
+        0, // end of rows
+    ]);
+}
+
+#[test]
+fn test_encode_empty_table_value() {
+    let table = MssqlTableValue::new("dbo.IdList");
+    let type_info = Encode::<Mssql>::produces(&table).unwrap();
+
+    let mut buf = Vec::new();
+    type_info.0.put_value(&mut buf, &table).unwrap();
+
+    // TVP_NULL_TOKEN, then the end of the (missing) metadata and of the rows
+    assert_eq!(buf, [0xff, 0xff, 0, 0]);
+
+    let table = MssqlTableValue::try_from_rows("dbo.IdList", Vec::<(i32,)>::new()).unwrap();
+    assert!(table.is_empty());
+}
+
+#[test]
+fn test_add_row_mismatch() {
+    let mut table = MssqlTableValue::new("dbo.IdList");
+    table.add_row((1_i32, "a"));
+
+    assert!(table.try_add_row((1_i64, "b")).is_err());
+    assert!(table.try_add_row((1_i32,)).is_err());
+    assert_eq!(table.len(), 1);
+
+    assert!(MssqlTableValue::try_from_rows("dbo.IdList", [(1_i32,), (2,)]).is_ok());
+
+    table.add_row((2_i32, "b"));
+    assert_eq!(table.len(), 2);
+}
@@ -0,0 +1,249 @@
+use time::{Date, Duration, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+use crate::decode::Decode;
+use crate::encode::{Encode, IsNull};
+use crate::error::BoxDynError;
+use crate::protocol::type_info::{DataType, TypeInfo};
+use crate::types::datetime::{self, Parts};
+use crate::{Mssql, MssqlTypeInfo, MssqlValueRef};
+use sqlx_core::types::Type;
+
+// the julian day of 0001-01-01, the epoch of DATE
+const EPOCH_JULIAN_DAY: i64 = 1_721_426;
+
+fn date_to_days(date: &Date) -> i64 {
+    date.to_julian_day() as i64 - EPOCH_JULIAN_DAY
+}
+
+fn days_to_date(days: i64) -> Result<Date, BoxDynError> {
+    Ok(Date::from_julian_day((days + EPOCH_JULIAN_DAY) as i32)?)
+}
+
+fn time_to_nanos(time: &Time) -> u64 {
+    (*time - Time::MIDNIGHT).whole_nanoseconds() as u64
+}
+
+fn nanos_to_time(nanos: u64) -> Time {
+    Time::MIDNIGHT + Duration::nanoseconds(nanos as i64)
+}
+
+fn datetime_to_parts(datetime: &PrimitiveDateTime, offset: i16) -> Parts {
+    Parts {
+        days: date_to_days(&datetime.date()),
+        nanos: time_to_nanos(&datetime.time()),
+        offset,
+    }
+}
+
+fn parts_to_datetime(parts: &Parts) -> Result<PrimitiveDateTime, BoxDynError> {
+    Ok(PrimitiveDateTime::new(
+        days_to_date(parts.days)?,
+        nanos_to_time(parts.nanos),
+    ))
+}
+
+impl Type<Mssql> for Date {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::new(DataType::DateN, 3))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        datetime::is_date(ty)
+    }
+}
+
+impl Encode<'_, Mssql> for Date {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        let parts = Parts {
+            days: date_to_days(self),
+            ..Parts::default()
+        };
+
+        datetime::encode(&Self::type_info().0, &parts, buf);
+
+        IsNull::No
+    }
+}
+
+impl Decode<'_, Mssql> for Date {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        let parts = datetime::decode(&value.type_info.0, value.as_bytes()?)?;
+
+        days_to_date(parts.days)
+    }
+}
+
+impl Type<Mssql> for Time {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::time(DataType::TimeN, datetime::SCALE))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        datetime::is_time(ty)
+    }
+}
+
+impl Encode<'_, Mssql> for Time {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        let parts = Parts {
+            nanos: time_to_nanos(self),
+            ..Parts::default()
+        };
+
+        datetime::encode(&Self::type_info().0, &parts, buf);
+
+        IsNull::No
+    }
+}
+
+impl Decode<'_, Mssql> for Time {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        let parts = datetime::decode(&value.type_info.0, value.as_bytes()?)?;
+
+        Ok(nanos_to_time(parts.nanos))
+    }
+}
+
+impl Type<Mssql> for PrimitiveDateTime {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::time(DataType::DateTime2N, datetime::SCALE))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        datetime::is_datetime(ty)
+    }
+}
+
+impl Encode<'_, Mssql> for PrimitiveDateTime {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        datetime::encode(&Self::type_info().0, &datetime_to_parts(self, 0), buf);
+
+        IsNull::No
+    }
+}
+
+impl Decode<'_, Mssql> for PrimitiveDateTime {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        let parts = datetime::decode(&value.type_info.0, value.as_bytes()?)?;
+
+        // the offset of a DATETIMEOFFSET is dropped, leaving the time in UTC
+        parts_to_datetime(&parts)
+    }
+}
+
+impl Type<Mssql> for OffsetDateTime {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::time(DataType::DateTimeOffsetN, datetime::SCALE))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        datetime::is_datetimeoffset(ty)
+    }
+}
+
+impl Encode<'_, Mssql> for OffsetDateTime {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        let offset = self.offset().whole_minutes();
+
+        let utc = self.to_offset(UtcOffset::UTC);
+        let parts = datetime_to_parts(&PrimitiveDateTime::new(utc.date(), utc.time()), offset);
+
+        datetime::encode(&Self::type_info().0, &parts, buf);
+
+        IsNull::No
+    }
+}
+
+impl Decode<'_, Mssql> for OffsetDateTime {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        let parts = datetime::decode(&value.type_info.0, value.as_bytes()?)?;
+
+        let offset = UtcOffset::from_whole_seconds(parts.offset as i32 * 60)?;
+
+        // SQL Server stores the time in UTC, which may be past the largest local time
+        parts_to_datetime(&parts)?
+            .assume_utc()
+            .checked_to_offset(offset)
+            .ok_or_else(|| "datetimeoffset out of range for OffsetDateTime".into())
+    }
+}
+
+#[test]
+fn test_datetimeoffset() {
+    use time::macros::datetime;
+
+    let value = datetime!(2023-06-15 13:45:30.1234567 +02:00);
+
+    let mut buf = Vec::new();
+    let _ = value.encode_by_ref(&mut buf);
+
+    #[rustfmt::skip]
+    assert_eq!(buf, [
+        // 11:45:30.1234567 in 100ns increments
+        0x87, 0x3f, 0xb8, 0x8e, 0x62,
+        // 738685 days since 0001-01-01
+        0x7d, 0x45, 0x0b,
+        // +120 minutes
+        0x78, 0x00,
+    ]);
+
+    let bytes = sqlx_core::bytes::Bytes::from(buf);
+
+    let decoded = <OffsetDateTime as Decode<Mssql>>::decode(MssqlValueRef {
+        type_info: <OffsetDateTime as Type<Mssql>>::type_info(),
+        data: Some(&bytes),
+    })
+    .unwrap();
+
+    assert_eq!(decoded, value);
+    assert_eq!(decoded.offset(), value.offset());
+}
+
+#[test]
+fn test_datetimeoffset_out_of_range() {
+    use time::macros::datetime;
+
+    let mut buf = Vec::new();
+    let _ = datetime!(9999-12-31 23:59 UTC).encode_by_ref(&mut buf);
+
+    // +14:00, which puts the local time in the year 10000
+    let len = buf.len();
+    buf[len - 2..].copy_from_slice(&840_i16.to_le_bytes());
+
+    let bytes = sqlx_core::bytes::Bytes::from(buf);
+
+    assert!(<OffsetDateTime as Decode<Mssql>>::decode(MssqlValueRef {
+        type_info: <OffsetDateTime as Type<Mssql>>::type_info(),
+        data: Some(&bytes),
+    })
+    .is_err());
+}
+
+#[test]
+fn test_date_out_of_range() {
+    use crate::MssqlArguments;
+    use time::Month;
+
+    let mut args = MssqlArguments::default();
+    args.add(Date::from_calendar_date(9999, Month::December, 31).unwrap());
+    args.add(
+        Date::from_calendar_date(1, Month::January, 1)
+            .unwrap()
+            .midnight(),
+    );
+    assert!(args.check("").is_ok());
+
+    // the day before 0001-01-01 would wrap around to a date far in the future
+    let mut args = MssqlArguments::default();
+    args.add(Date::from_calendar_date(0, Month::December, 31).unwrap());
+    assert!(args.check("").is_err());
+
+    let mut args = MssqlArguments::default();
+    args.add(
+        Date::from_calendar_date(-1, Month::January, 1)
+            .unwrap()
+            .midnight()
+            .assume_utc(),
+    );
+    assert!(args.check("").is_err());
+}
@@ -0,0 +1,65 @@
+use uuid::Uuid;
+
+use crate::decode::Decode;
+use crate::encode::{Encode, IsNull};
+use crate::error::BoxDynError;
+use crate::protocol::type_info::{DataType, TypeInfo};
+use crate::{Mssql, MssqlTypeInfo, MssqlValueRef};
+use sqlx_core::types::Type;
+
+impl Type<Mssql> for Uuid {
+    fn type_info() -> MssqlTypeInfo {
+        MssqlTypeInfo(TypeInfo::new(DataType::Guid, 16))
+    }
+
+    fn compatible(ty: &MssqlTypeInfo) -> bool {
+        matches!(ty.0.ty, DataType::Guid)
+    }
+}
+
+// a UNIQUEIDENTIFIER is sent as a GUID, which stores its first three groups little-endian
+// https://docs.microsoft.com/en-us/windows/win32/api/guiddef/ns-guiddef-guid
+
+impl Encode<'_, Mssql> for Uuid {
+    fn encode_by_ref(&self, buf: &mut Vec<u8>) -> IsNull {
+        buf.extend(&self.to_bytes_le());
+
+        IsNull::No
+    }
+}
+
+impl Decode<'_, Mssql> for Uuid {
+    fn decode(value: MssqlValueRef<'_>) -> Result<Self, BoxDynError> {
+        let bytes = value.as_bytes()?;
+
+        let bytes = bytes
+            .try_into()
+            .map_err(|_| format!("invalid UNIQUEIDENTIFIER value of {} bytes", bytes.len()))?;
+
+        Ok(Uuid::from_bytes_le(bytes))
+    }
+}
+
+#[test]
+fn test_uuid() {
+    let value = Uuid::parse_str("6f9619ff-8b86-d011-b42d-00c04fc964ff").unwrap();
+
+    let mut buf = Vec::new();
+    let _ = value.encode_by_ref(&mut buf);
+
+    #[rustfmt::skip]
+    assert_eq!(buf, [
+        0xff, 0x19, 0x96, 0x6f, 0x86, 0x8b, 0x11, 0xd0,
+        0xb4, 0x2d, 0x00, 0xc0, 0x4f, 0xc9, 0x64, 0xff,
+    ]);
+
+    let bytes = sqlx_core::bytes::Bytes::from(buf);
+
+    let decoded = <Uuid as Decode<Mssql>>::decode(MssqlValueRef {
+        type_info: <Uuid as Type<Mssql>>::type_info(),
+        data: Some(&bytes),
+    })
+    .unwrap();
+
+    assert_eq!(decoded, value);
+}